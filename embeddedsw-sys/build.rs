@@ -1,4 +1,5 @@
 use std::{
+    collections::BTreeMap,
     env,
     fs::{self, File},
     io::{self, BufReader, Read, Write},
@@ -7,8 +8,22 @@ use std::{
 };
 
 fn main() {
+    // re-run if build.rs or the XSA file path is changed
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=XSA_PATH");
+
     // Get XSA file path
-    let xsa_path = env!("XSA_PATH");
+    let xsa_path = match env::var("XSA_PATH") {
+        Ok(xsa_path) => xsa_path,
+        Err(_) => {
+            // Without a hardware design there is nothing to bind,
+            // so empty bindings are generated for host builds such as
+            // `cargo test --lib`.
+            println!("cargo:warning=XSA_PATH is not set, skip generating the bindings");
+            gen_empty_bindings().expect("Couldn't write empty bindings");
+            return;
+        }
+    };
 
     // Gen platform script
     let mut platform = Platform::new();
@@ -94,6 +109,12 @@ fn main() {
     #[cfg(feature = "xilffs")]
     let bind_builder = bind_builder.header("wrapper_xilffs.h");
 
+    #[cfg(feature = "xipipsu")]
+    let bind_builder = bind_builder.header("wrapper_xipipsu.h");
+
+    #[cfg(feature = "xuartps")]
+    let bind_builder = bind_builder.header("wrapper_xuartps.h");
+
     let bind_builder = bind_builder
         .generate()
         .expect("Failed to generate bindings");
@@ -103,6 +124,18 @@ fn main() {
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write biindings");
 
+    // Generate the interrupt ID enumeration from xparameters.h
+    let interrupts = Interrupts::parse(bsp_include_path);
+    interrupts
+        .gen_rust(&out_path.join("interrupt.rs"))
+        .expect("Couldn't write interrupt.rs");
+
+    // Generate the status code enumeration from xstatus.h
+    let status_codes = StatusCodes::parse(bsp_include_path);
+    status_codes
+        .gen_rust(&out_path.join("xstatus.rs"))
+        .expect("Couldn't write xstatus.rs");
+
     // Get a embedded-sys root path
     let pwd = Command::new("pwd")
         .output()
@@ -120,9 +153,14 @@ fn main() {
     println!(
         "cargo:rustc-link-arg=-Wl,--start-group,-lc,-lgcc,-lxil,-end-group"
     );
+}
 
-    // re-run if build.rs is changed
-    println!("cargo:rerun-if-changed=build.rs");
+fn gen_empty_bindings() -> io::Result<()> {
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    for file in ["bindings.rs", "interrupt.rs", "xstatus.rs"] {
+        File::create(out_path.join(file))?;
+    }
+    Ok(())
 }
 
 struct XSpfm {
@@ -172,6 +210,384 @@ impl XSpfm {
     }
 }
 
+/// Interrupt IDs defined in xparameters.h and xparameters_ps.h
+///
+/// - `XPAR_FABRIC_*_INTR` (PL fabric interrupts)
+/// - `XPAR_*_INTR` defined as `XPS_*_INT_ID` (PS peripherals)
+/// - `XPS_*_INT_ID` (PS interrupts)
+///
+/// The other `XPAR_*_INTR`, such as the ones of AXI INTC,
+/// are not GIC interrupt IDs, so they are skipped.
+///
+/// When two macros have the same CamelCase name (e.g. `CAN_0` and `CAN0`),
+/// the later one keeps the underscores in its variant name (`Can_0`),
+/// or takes a numeric suffix (`Can0_2`) if it has no underscores.
+struct Interrupts {
+    // variant name -> (macro name, interrupt ID)
+    variants: BTreeMap<String, (String, u32)>,
+}
+
+impl Interrupts {
+    fn parse(bsp_include_path: &Path) -> Interrupts {
+        let mut defines = BTreeMap::new();
+        for header in ["xparameters_ps.h", "xparameters.h"] {
+            let path = bsp_include_path.join(header);
+            let contents = fs::read_to_string(&path).unwrap_or_else(|e| {
+                panic!("Failed to open {}: {}", path.display(), e)
+            });
+            for line in contents.lines() {
+                let mut tokens = line.trim().splitn(3, char::is_whitespace);
+                if let (Some("#define"), Some(name), Some(value)) =
+                    (tokens.next(), tokens.next(), tokens.next())
+                {
+                    let value = match value.find("/*") {
+                        Some(i) => &value[..i],
+                        None => value,
+                    };
+                    defines.insert(name.to_string(), value.trim().to_string());
+                }
+            }
+        }
+
+        let mut variants = BTreeMap::new();
+        for name in defines.keys() {
+            let stem = if let Some(stem) = name
+                .strip_prefix("XPAR_")
+                .and_then(|name| name.strip_suffix("_INTR"))
+            {
+                if !stem.starts_with("FABRIC_")
+                    && !refers_to_ps_int_id(&defines, name, 0)
+                {
+                    continue;
+                }
+                stem
+            } else if let Some(stem) = name
+                .strip_prefix("XPS_")
+                .and_then(|name| name.strip_suffix("_INT_ID"))
+            {
+                stem
+            } else {
+                continue;
+            };
+
+            let id = match eval_define(&defines, name, 0) {
+                Some(id) => id,
+                None => {
+                    println!(
+                        "cargo:warning=Couldn't evaluate {} = {}, skipped",
+                        name, defines[name]
+                    );
+                    continue;
+                }
+            };
+
+            let mut variant = camel_case(stem);
+            if variants.contains_key(&variant) {
+                let snake = snake_camel_case(stem);
+                variant = snake.clone();
+                let mut n = 2;
+                while variants.contains_key(&variant) {
+                    variant = format!("{}_{}", snake, n);
+                    n += 1;
+                }
+                println!(
+                    "cargo:warning={} is named Interrupt::{} \
+                     to avoid a name collision",
+                    name, variant
+                );
+            }
+            variants.insert(variant, (name.clone(), id));
+        }
+
+        Interrupts { variants }
+    }
+
+    fn gen_rust(&self, path: &Path) -> Result<(), io::Error> {
+        let mut contents = String::new();
+        contents.push_str(
+            "/// Interrupt IDs generated from xparameters.h\n\
+             #[derive(Debug, Copy, Clone, PartialEq, Eq)]\n\
+             pub enum Interrupt {\n",
+        );
+        for i in 0..16 {
+            contents.push_str(&format!(
+                "    /// Software generated interrupt {}\n    Sgi{},\n",
+                i, i
+            ));
+        }
+        for (variant, (name, _)) in &self.variants {
+            contents.push_str(&format!("    /// {}\n    {},\n", name, variant));
+        }
+        contents.push_str(
+            "}\n\n\
+             impl Interrupt {\n\
+             \x20   /// This function takes the interrupt ID.\n\
+             \x20   pub const fn id(self) -> u32 {\n\
+             \x20       match self {\n",
+        );
+        for i in 0..16 {
+            contents.push_str(&format!(
+                "            Interrupt::Sgi{} => {},\n",
+                i, i
+            ));
+        }
+        for (variant, (_, id)) in &self.variants {
+            contents.push_str(&format!(
+                "            Interrupt::{} => {},\n",
+                variant, id
+            ));
+        }
+        contents.push_str("        }\n    }\n}\n");
+
+        let mut file = File::create(path)?;
+        file.write_all(contents.as_bytes())
+    }
+}
+
+/// Status codes defined in xstatus.h (`XST_*`)
+struct StatusCodes {
+    // (variant name, macro name, status code) in the order of definitions
+    codes: Vec<(String, String, i32)>,
+}
+
+impl StatusCodes {
+    fn parse(bsp_include_path: &Path) -> StatusCodes {
+        let contents = fs::read_to_string(bsp_include_path.join("xstatus.h"))
+            .expect("Failed to open xstatus.h");
+
+        let mut codes: Vec<(String, String, i32)> = Vec::new();
+        for line in contents.lines() {
+            let mut tokens = line.split_whitespace();
+            if let (Some("#define"), Some(name), Some(value)) =
+                (tokens.next(), tokens.next(), tokens.next())
+            {
+                let stem = match name.strip_prefix("XST_") {
+                    Some(stem) => stem,
+                    None => continue,
+                };
+                let variant = camel_case(stem);
+                if variant == "Unknown"
+                    || codes.iter().any(|(v, _, _)| *v == variant)
+                {
+                    continue;
+                }
+                if let Some(code) = parse_int(value) {
+                    codes.push((variant, name.to_string(), code as i32));
+                }
+            }
+        }
+
+        StatusCodes { codes }
+    }
+
+    fn gen_rust(&self, path: &Path) -> Result<(), io::Error> {
+        let mut contents = String::new();
+        contents.push_str(
+            "/// Status codes generated from xstatus.h\n\
+             #[derive(Debug, Copy, Clone, PartialEq, Eq)]\n\
+             pub enum XStatus {\n",
+        );
+        for (variant, name, _) in &self.codes {
+            contents.push_str(&format!("    /// {}\n    {},\n", name, variant));
+        }
+        contents.push_str(
+            "    /// A status code which is not defined in xstatus.h\n\
+             \x20   Unknown(i32),\n\
+             }\n\n\
+             impl XStatus {\n\
+             \x20   /// This function takes the status code.\n\
+             \x20   pub const fn code(self) -> i32 {\n\
+             \x20       match self {\n",
+        );
+        for (variant, _, code) in &self.codes {
+            contents.push_str(&format!(
+                "            XStatus::{} => {},\n",
+                variant, code
+            ));
+        }
+        contents.push_str(
+            "            XStatus::Unknown(code) => code,\n\
+             \x20       }\n\
+             \x20   }\n\n\
+             \x20   /// This function takes the macro name of the status code.\n\
+             \x20   pub const fn name(self) -> &'static str {\n\
+             \x20       match self {\n",
+        );
+        for (variant, name, _) in &self.codes {
+            contents.push_str(&format!(
+                "            XStatus::{} => \"{}\",\n",
+                variant, name
+            ));
+        }
+        contents.push_str(
+            "            XStatus::Unknown(_) => \"XST_UNKNOWN\",\n\
+             \x20       }\n\
+             \x20   }\n\
+             }\n\n\
+             impl From<i32> for XStatus {\n\
+             \x20   #[allow(unreachable_patterns)]\n\
+             \x20   fn from(code: i32) -> XStatus {\n\
+             \x20       match code {\n",
+        );
+        for (variant, _, code) in &self.codes {
+            contents.push_str(&format!(
+                "            {} => XStatus::{},\n",
+                code, variant
+            ));
+        }
+        contents.push_str(
+            "            code => XStatus::Unknown(code),\n\
+             \x20       }\n\
+             \x20   }\n\
+             }\n\n\
+             impl From<u32> for XStatus {\n\
+             \x20   fn from(code: u32) -> XStatus {\n\
+             \x20       XStatus::from(code as i32)\n\
+             \x20   }\n\
+             }\n\n\
+             impl core::fmt::Display for XStatus {\n\
+             \x20   fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {\n\
+             \x20       write!(f, \"{} ({})\", self.name(), self.code())\n\
+             \x20   }\n\
+             }\n\n\
+             impl core::error::Error for XStatus {}\n",
+        );
+
+        let mut file = File::create(path)?;
+        file.write_all(contents.as_bytes())
+    }
+}
+
+/// Evaluate a macro which is defined by integer literals,
+/// other macros, `+`, `-`, parentheses and C casts
+/// such as `((u32)53U + 32U)`.
+fn eval_define(
+    defines: &BTreeMap<String, String>,
+    name: &str,
+    depth: usize,
+) -> Option<u32> {
+    if depth > 16 {
+        return None;
+    }
+    let value = defines.get(name)?;
+
+    let mut result: i64 = 0;
+    let mut sign = 1;
+    for token in value
+        .split(|c: char| c == '(' || c == ')' || c.is_whitespace())
+        .flat_map(|token| split_keep(token, &['+', '-']))
+        .filter(|token| !token.is_empty())
+    {
+        match token {
+            "+" => sign = 1,
+            "-" => sign = -1,
+            token if C_CAST_TYPES.contains(&token) => {}
+            token => {
+                let n = parse_int(token).or_else(|| {
+                    eval_define(defines, token, depth + 1).map(|n| n as i64)
+                })?;
+                result += sign * n;
+            }
+        }
+    }
+
+    u32::try_from(result).ok()
+}
+
+/// The type names which appear in C casts such as `(u32)`
+const C_CAST_TYPES: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "s8", "s16", "s32", "s64", "int", "unsigned",
+    "long", "UINTPTR",
+];
+
+/// This function checks whether the define is an `XPS_*_INT_ID`,
+/// or is defined as one of them.
+fn refers_to_ps_int_id(
+    defines: &BTreeMap<String, String>,
+    name: &str,
+    depth: usize,
+) -> bool {
+    if name.starts_with("XPS_") && name.ends_with("_INT_ID") {
+        return true;
+    }
+    if depth > 16 {
+        return false;
+    }
+    match defines.get(name) {
+        Some(value) => value
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .any(|token| {
+                defines.contains_key(token)
+                    && refers_to_ps_int_id(defines, token, depth + 1)
+            }),
+        None => false,
+    }
+}
+
+fn split_keep<'a>(token: &'a str, seps: &[char]) -> Vec<&'a str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    for (i, c) in token.char_indices() {
+        if seps.contains(&c) {
+            tokens.push(&token[start..i]);
+            tokens.push(&token[i..i + 1]);
+            start = i + 1;
+        }
+    }
+    tokens.push(&token[start..]);
+    tokens
+}
+
+fn parse_int(token: &str) -> Option<i64> {
+    let token = token
+        .trim_end_matches(|c| c == 'U' || c == 'u' || c == 'L' || c == 'l');
+    if let Some(hex) = token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+/// FABRIC_AXI_DMA_0_MM2S_INTROUT -> FabricAxiDma0Mm2sIntrout
+fn camel_case(name: &str) -> String {
+    let mut camel = String::new();
+    for word in name.split('_').filter(|word| !word.is_empty()) {
+        let mut chars = word.chars();
+        if let Some(c) = chars.next() {
+            camel.push(c.to_ascii_uppercase());
+            camel.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+    if camel.starts_with(|c: char| c.is_ascii_digit()) {
+        camel.insert_str(0, "Irq");
+    }
+    camel
+}
+
+/// CAN_0 -> Can_0
+fn snake_camel_case(name: &str) -> String {
+    let mut camel = name
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().map(|c| c.to_ascii_uppercase());
+            first
+                .into_iter()
+                .chain(chars.map(|c| c.to_ascii_lowercase()))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("_");
+    if camel.starts_with(|c: char| c.is_ascii_digit()) {
+        camel.insert_str(0, "Irq");
+    }
+    camel
+}
+
 #[derive(Debug, Copy, Clone)]
 enum FeatureKind {
     Base,
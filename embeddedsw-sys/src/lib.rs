@@ -4,3 +4,13 @@
 #![allow(non_snake_case)]
 
 include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// Interrupt IDs generated from the hardware design
+pub mod interrupt {
+    include!(concat!(env!("OUT_DIR"), "/interrupt.rs"));
+}
+
+/// Status codes generated from xstatus.h
+pub mod xstatus {
+    include!(concat!(env!("OUT_DIR"), "/xstatus.rs"));
+}
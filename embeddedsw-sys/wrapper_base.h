@@ -1,3 +1,7 @@
 #include "xil_printf.h"
+#include "xil_assert.h"
 #include "xscugic.h"
-#include "stdlib.h"
\ No newline at end of file
+#include "xil_cache.h"
+#include "xil_mpu.h"
+#include "stdlib.h"
+#include "xtime_l.h"
\ No newline at end of file
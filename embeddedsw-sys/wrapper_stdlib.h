@@ -1 +1,2 @@
 #include "stdlib.h"
+#include "malloc.h"
@@ -0,0 +1 @@
+#include "xipipsu.h"
\ No newline at end of file
@@ -0,0 +1 @@
+#include "xuartps.h"
\ No newline at end of file
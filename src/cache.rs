@@ -0,0 +1,193 @@
+extern crate embeddedsw_sys;
+use core::mem::size_of_val;
+use embeddedsw_sys as esys;
+
+/// The size of the cache line of Cortex-R5.
+pub const CACHE_LINE_SIZE: usize = 32;
+
+//-------------------------------------------------------------------------------------------------
+// CacheRange
+//-------------------------------------------------------------------------------------------------
+
+/// An address range for cache maintenance.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::cache::{self, CacheRange};
+///
+/// #[repr(align(32))]
+/// struct Shared([u32; 8]);
+/// static mut SHARED: Shared = Shared([0; 8]);
+///
+/// let range = CacheRange::from_ref(unsafe { &*core::ptr::addr_of!(SHARED) });
+/// cache::dcache_flush_range(range);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CacheRange {
+    addr: usize,
+    len: usize,
+}
+
+impl CacheRange {
+    /// This function creates a range from the address and the length in bytes.
+    pub const fn new(addr: usize, len: usize) -> Self {
+        Self { addr, len }
+    }
+
+    /// This function creates a range which covers the value.
+    pub fn from_ref<T: ?Sized>(value: &T) -> Self {
+        Self::new(value as *const T as *const u8 as usize, size_of_val(value))
+    }
+
+    /// This function takes the start address.
+    pub const fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// This function takes the length in bytes.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// This function checks whether the range is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// This function checks whether both ends are on cache line boundaries.
+    pub const fn is_aligned(&self) -> bool {
+        self.addr % CACHE_LINE_SIZE == 0 && self.len % CACHE_LINE_SIZE == 0
+    }
+
+    /// This function takes the smallest range of whole cache lines
+    /// which covers the range.
+    pub const fn expand_to_lines(&self) -> Self {
+        let start = self.addr & !(CACHE_LINE_SIZE - 1);
+        let end = (self.addr + self.len + CACHE_LINE_SIZE - 1)
+            & !(CACHE_LINE_SIZE - 1);
+        Self::new(start, end - start)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// D-cache
+//-------------------------------------------------------------------------------------------------
+
+/// This function enables the D-cache.
+pub fn dcache_enable() {
+    unsafe { esys::Xil_DCacheEnable() }
+}
+
+/// This function flushes and disables the D-cache.
+pub fn dcache_disable() {
+    unsafe { esys::Xil_DCacheDisable() }
+}
+
+/// This function invalidates the whole D-cache.
+///
+/// # Safety
+/// All data which has not been written back to memory is lost,
+/// including the stack and the statics.
+pub unsafe fn dcache_invalidate() {
+    esys::Xil_DCacheInvalidate()
+}
+
+/// This function invalidates the D-cache lines of the range,
+/// so the CPU reads the data which other masters wrote.
+///
+/// The lines at both ends are invalidated as a whole,
+/// so the range should be aligned to cache lines.
+/// It is checked by a debug assertion.
+///
+/// # Safety
+/// The data in the lines which has not been written back is lost,
+/// including other data which shares the lines with the range.
+pub unsafe fn dcache_invalidate_range(range: CacheRange) {
+    debug_assert!(
+        range.is_aligned(),
+        "the range to invalidate is not cache-line aligned: {:?}",
+        range
+    );
+    esys::Xil_DCacheInvalidateRange(range.addr() as _, range.len() as u32)
+}
+
+/// This function writes back and invalidates the whole D-cache.
+pub fn dcache_flush() {
+    unsafe { esys::Xil_DCacheFlush() }
+}
+
+/// This function writes back and invalidates the D-cache lines of the range,
+/// so other masters read the data which the CPU wrote.
+///
+/// Flushing the lines at both ends does not lose data,
+/// but the range should be aligned to cache lines if other masters
+/// also write the memory. It is checked by a debug assertion.
+pub fn dcache_flush_range(range: CacheRange) {
+    debug_assert!(
+        range.is_aligned(),
+        "the range to flush is not cache-line aligned: {:?}",
+        range
+    );
+    unsafe { esys::Xil_DCacheFlushRange(range.addr() as _, range.len() as u32) }
+}
+
+/// This function invalidates the D-cache line which contains the address.
+///
+/// # Safety
+/// The data in the line which has not been written back is lost.
+pub unsafe fn dcache_invalidate_line(addr: usize) {
+    esys::Xil_DCacheInvalidateLine(addr as _)
+}
+
+/// This function writes back and invalidates
+/// the D-cache line which contains the address.
+pub fn dcache_flush_line(addr: usize) {
+    unsafe { esys::Xil_DCacheFlushLine(addr as _) }
+}
+
+/// This function writes back the D-cache line which contains the address
+/// without invalidating it.
+pub fn dcache_store_line(addr: usize) {
+    unsafe { esys::Xil_DCacheStoreLine(addr as _) }
+}
+
+//-------------------------------------------------------------------------------------------------
+// I-cache
+//-------------------------------------------------------------------------------------------------
+
+/// This function enables the I-cache.
+pub fn icache_enable() {
+    unsafe { esys::Xil_ICacheEnable() }
+}
+
+/// This function disables the I-cache.
+pub fn icache_disable() {
+    unsafe { esys::Xil_ICacheDisable() }
+}
+
+/// This function invalidates the whole I-cache.
+pub fn icache_invalidate() {
+    unsafe { esys::Xil_ICacheInvalidate() }
+}
+
+/// This function invalidates the I-cache lines of the range.
+///
+/// After loading code into memory, flush the D-cache of the range
+/// and then invalidate the I-cache of it.
+/// The range should be aligned to cache lines,
+/// and it is checked by a debug assertion.
+pub fn icache_invalidate_range(range: CacheRange) {
+    debug_assert!(
+        range.is_aligned(),
+        "the range to invalidate is not cache-line aligned: {:?}",
+        range
+    );
+    unsafe {
+        esys::Xil_ICacheInvalidateRange(range.addr() as _, range.len() as u32)
+    }
+}
+
+/// This function invalidates the I-cache line which contains the address.
+pub fn icache_invalidate_line(addr: usize) {
+    unsafe { esys::Xil_ICacheInvalidateLine(addr as _) }
+}
@@ -0,0 +1,813 @@
+extern crate embeddedsw_sys;
+use core::{
+    cell::UnsafeCell,
+    fmt::{self, Write},
+    ptr,
+    str::FromStr,
+    sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+    time::Duration,
+};
+use embeddedsw_sys as esys;
+
+use crate::{
+    xscugic::{interrupt_free, InterruptSource, XScuGic},
+    xstatus::XStatus,
+    xtime::Instant,
+};
+#[cfg(not(feature = "semihosting"))]
+use crate::xscugic::mfcpsr;
+
+/// The base address of the UART of `outbyte`.
+const UART_BASE: usize = esys::STDOUT_BASEADDRESS as usize;
+
+// Registers of the UART (Cadence UART of ZynqMP).
+const UART_IER: usize = 0x08;
+const UART_IDR: usize = 0x0c;
+const UART_ISR: usize = 0x14;
+const UART_SR: usize = 0x2c;
+const UART_FIFO: usize = 0x30;
+
+/// The TX FIFO is empty, in SR and the interrupt registers.
+const UART_IXR_TXEMPTY: u32 = 0x08;
+/// The TX FIFO is full, in SR.
+const UART_SR_TXFULL: u32 = 0x10;
+/// The RX FIFO is empty, in SR.
+#[cfg(not(feature = "semihosting"))]
+const UART_SR_RXEMPTY: u32 = 0x02;
+
+/// The size of the buffer of a lock.
+pub const LOCK_BUFFER_SIZE: usize = 128;
+/// The size of the TX ring buffer.
+pub const TX_RING_SIZE: usize = 2048;
+
+#[cfg(not(feature = "semihosting"))]
+const CPSR_MODE_MASK: u32 = 0x1f;
+#[cfg(not(feature = "semihosting"))]
+const CPSR_MODE_USR: u32 = 0x10;
+#[cfg(not(feature = "semihosting"))]
+const CPSR_MODE_SYS: u32 = 0x1f;
+
+//-------------------------------------------------------------------------------------------------
+// Policy
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for what output in interrupt context does
+/// when it cannot be written without waiting for the UART.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Policy {
+    /// Drop the output and count the dropped bytes. It is the default.
+    Drop = 0,
+    /// Wait for the UART with interrupts masked.
+    Block = 1,
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(Policy::Drop as u8);
+static DROPPED: AtomicUsize = AtomicUsize::new(0);
+static TX_RING_ENABLED: AtomicBool = AtomicBool::new(false);
+/// Whether a writer is writing to the UART of `outbyte` without the TX ring.
+#[cfg(not(feature = "semihosting"))]
+static STDOUT_CLAIMED: AtomicBool = AtomicBool::new(false);
+/// Whether a writer is writing to the UART of `Stderr`,
+/// if it is not the UART of `outbyte`.
+#[cfg(not(feature = "semihosting"))]
+static STDERR_CLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// This function selects the policy of output in interrupt context.
+/// Output in thread context always waits.
+pub fn set_interrupt_policy(policy: Policy) {
+    POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+/// This function takes the number of bytes dropped by `Policy::Drop`.
+pub fn dropped() -> usize {
+    DROPPED.load(Ordering::Relaxed)
+}
+
+/// This function checks whether the CPU runs an exception handler,
+/// that is, it is not in USR or SYS mode.
+#[cfg(not(feature = "semihosting"))]
+fn in_interrupt() -> bool {
+    let mode = unsafe { mfcpsr() } & CPSR_MODE_MASK;
+    mode != CPSR_MODE_USR && mode != CPSR_MODE_SYS
+}
+
+//-------------------------------------------------------------------------------------------------
+// LineEnding
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the line endings which `\n` is translated into.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`
+    Lf = 0,
+    /// `\r\n`. It is the default.
+    CrLf = 1,
+}
+
+impl LineEnding {
+    fn as_bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+static LINE_ENDING: AtomicU8 = AtomicU8::new(LineEnding::CrLf as u8);
+
+/// This function selects the line ending of the console output.
+///
+/// `\n` is translated into it when it is written.
+/// `\r\n` is kept as it is, and `\r` just after `\n` is removed,
+/// so the output of the old `println!`, which ended with `\n\r`,
+/// has the same line endings.
+pub fn set_line_ending(ending: LineEnding) {
+    LINE_ENDING.store(ending as u8, Ordering::Relaxed);
+}
+
+/// This function takes the line ending of the console output.
+pub fn line_ending() -> LineEnding {
+    match LINE_ENDING.load(Ordering::Relaxed) {
+        0 => LineEnding::Lf,
+        _ => LineEnding::CrLf,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Stdout
+//-------------------------------------------------------------------------------------------------
+
+/// A handle of the UART of `outbyte`.
+#[derive(Debug, Copy, Clone)]
+pub struct Stdout {
+    _private: (),
+}
+
+/// This function takes the handle of the UART of `outbyte`.
+///
+/// # Example
+/// ```
+/// use core::fmt::Write;
+/// use embeddedsw_rs::console;
+///
+/// let mut out = console::stdout().lock();
+/// write!(out, "x = {}, ", 1).unwrap();
+/// write!(out, "y = {}\n", 2).unwrap();
+/// // the line is written at once when `out` is dropped.
+/// ```
+pub fn stdout() -> Stdout {
+    Stdout { _private: () }
+}
+
+impl Stdout {
+    /// This function takes a writer which buffers the output.
+    ///
+    /// The buffer is written at once when the lock is dropped
+    /// or it is full, so output from interrupt handlers
+    /// is not mixed into the middle of it.
+    /// Without the TX ring, `Policy::Block` is the exception:
+    /// the output of an interrupt handler can come between the chunks
+    /// which fill the TX FIFO.
+    pub fn lock(&self) -> StdoutLock {
+        StdoutLock {
+            inner: Buffer::new(Sink::Stdout),
+        }
+    }
+}
+
+/// A writer of `Stdout`, which buffers up to `LOCK_BUFFER_SIZE` bytes.
+pub struct StdoutLock {
+    inner: Buffer,
+}
+
+impl StdoutLock {
+    /// This function writes the buffered output to the UART.
+    pub fn flush(&mut self) {
+        self.inner.flush()
+    }
+}
+
+impl Write for StdoutLock {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Stderr
+//-------------------------------------------------------------------------------------------------
+
+/// The base address of the UART of `Stderr`.
+#[cfg(not(feature = "semihosting"))]
+static STDERR_BASE: AtomicUsize = AtomicUsize::new(UART_BASE);
+
+/// This function selects the initialized UART for `Stderr`,
+/// which is written by polling.
+/// It is the UART of `outbyte` by default.
+/// With the `semihosting` feature, `Stderr` is written to the host,
+/// so this function is not available.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{console, eprintln, peripherals::Peripherals, raw};
+///
+/// let mut p = Peripherals::take().unwrap();
+/// let uart = p.uartps(raw::XPAR_XUARTPS_1_DEVICE_ID as u16).unwrap();
+/// console::set_stderr_uart(uart);
+/// eprintln!("to UART1");
+/// ```
+#[cfg(all(feature = "xuartps", not(feature = "semihosting")))]
+pub fn set_stderr_uart(uart: &crate::xuartps::XUartPs) {
+    STDERR_BASE.store(uart.base_addr(), Ordering::Relaxed);
+}
+
+/// A handle of the UART for errors and debug output.
+#[derive(Debug, Copy, Clone)]
+pub struct Stderr {
+    _private: (),
+}
+
+/// This function takes the handle of the UART of `eprint!`.
+pub fn stderr() -> Stderr {
+    Stderr { _private: () }
+}
+
+impl Stderr {
+    /// This function takes a writer which buffers the output like `Stdout`.
+    pub fn lock(&self) -> StderrLock {
+        StderrLock {
+            inner: Buffer::new(Sink::Stderr),
+        }
+    }
+}
+
+/// A writer of `Stderr`, which buffers up to `LOCK_BUFFER_SIZE` bytes.
+pub struct StderrLock {
+    inner: Buffer,
+}
+
+impl StderrLock {
+    /// This function writes the buffered output to the UART.
+    pub fn flush(&mut self) {
+        self.inner.flush()
+    }
+}
+
+impl Write for StderrLock {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_bytes(s.as_bytes());
+        Ok(())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Buffer
+//-------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Sink {
+    Stdout,
+    Stderr,
+}
+
+/// The buffer of a lock, which translates line endings.
+struct Buffer {
+    buf: [u8; LOCK_BUFFER_SIZE],
+    len: usize,
+    sink: Sink,
+    ending: LineEnding,
+    after_lf: bool,
+    after_cr: bool,
+}
+
+impl Buffer {
+    fn new(sink: Sink) -> Self {
+        Self {
+            buf: [0; LOCK_BUFFER_SIZE],
+            len: 0,
+            sink,
+            ending: line_ending(),
+            after_lf: false,
+            after_cr: false,
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            match *b {
+                b'\n' => {
+                    if self.after_cr {
+                        self.push(b'\n');
+                    } else {
+                        for b in self.ending.as_bytes() {
+                            self.push(*b);
+                        }
+                    }
+                    self.after_lf = true;
+                    self.after_cr = false;
+                }
+                b'\r' if self.after_lf => self.after_lf = false,
+                b => {
+                    self.push(b);
+                    self.after_lf = false;
+                    self.after_cr = b == b'\r';
+                }
+            }
+        }
+    }
+
+    fn push(&mut self, b: u8) {
+        if self.len == LOCK_BUFFER_SIZE {
+            self.flush();
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+    }
+
+    fn flush(&mut self) {
+        if self.len == 0 {
+            return;
+        }
+        let bytes = &self.buf[..self.len];
+        match self.sink {
+            #[cfg(feature = "semihosting")]
+            Sink::Stdout => crate::semihosting::write_stdout(bytes),
+            #[cfg(feature = "semihosting")]
+            Sink::Stderr => crate::semihosting::write_stderr(bytes),
+            #[cfg(not(feature = "semihosting"))]
+            Sink::Stdout => commit(bytes),
+            #[cfg(not(feature = "semihosting"))]
+            Sink::Stderr => {
+                let base = STDERR_BASE.load(Ordering::Relaxed);
+                if base == UART_BASE {
+                    commit(bytes)
+                } else {
+                    commit_to_uart(base, bytes)
+                }
+            }
+        }
+        self.len = 0;
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// This function writes the bytes at once.
+#[cfg(not(feature = "semihosting"))]
+fn commit(bytes: &[u8]) {
+    if TX_RING_ENABLED.load(Ordering::Acquire) {
+        commit_to_ring(bytes)
+    } else {
+        commit_to_uart(UART_BASE, bytes)
+    }
+}
+
+/// Without the TX ring, the writer claims the UART and writes the bytes
+/// by polling. Interrupts are masked only while a chunk fills the TX FIFO,
+/// and the writer waits for the FIFO with interrupts enabled.
+///
+/// An interrupt handler which preempts the writer drops its output
+/// with `Policy::Drop`, and writes it between the chunks with `Policy::Block`.
+#[cfg(not(feature = "semihosting"))]
+fn commit_to_uart(base: usize, bytes: &[u8]) {
+    let drop =
+        in_interrupt() && POLICY.load(Ordering::Relaxed) == Policy::Drop as u8;
+    let flag = if base == UART_BASE {
+        &STDOUT_CLAIMED
+    } else {
+        &STDERR_CLAIMED
+    };
+    let claimed = !flag.swap(true, Ordering::Acquire);
+    if !claimed && drop {
+        DROPPED.fetch_add(bytes.len(), Ordering::Relaxed);
+        return;
+    }
+
+    let mut rest = bytes;
+    loop {
+        let n = interrupt_free(|| {
+            let mut n = 0;
+            while n < rest.len() && !tx_full(base) {
+                write_reg(base, UART_FIFO, rest[n] as u32);
+                n += 1;
+            }
+            n
+        });
+        rest = &rest[n..];
+        if rest.is_empty() {
+            break;
+        }
+        if drop {
+            DROPPED.fetch_add(rest.len(), Ordering::Relaxed);
+            break;
+        }
+        while tx_full(base) {}
+    }
+
+    if claimed {
+        flag.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(not(feature = "semihosting"))]
+fn commit_to_ring(bytes: &[u8]) {
+    let drop =
+        in_interrupt() && POLICY.load(Ordering::Relaxed) == Policy::Drop as u8;
+    loop {
+        let done = interrupt_free(|| {
+            let ring = unsafe { &mut *TX_RING.0.get() };
+            if ring.free() >= bytes.len() {
+                ring.push(bytes);
+                kick(ring);
+                return true;
+            }
+            if drop {
+                DROPPED.fetch_add(bytes.len(), Ordering::Relaxed);
+                return true;
+            }
+            // the TX-empty interrupt does not drain the ring while it is
+            // masked, by the CPSR or by the priority mask of the GIC
+            // (e.g. `Resource::lock`), so the writer refills the FIFO.
+            kick(ring);
+            false
+        });
+        if done {
+            return;
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Stdin
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the errors of console input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReadError {
+    /// No input arrived before the timeout.
+    Timeout,
+    /// The line is not valid UTF-8.
+    InvalidUtf8,
+    /// The line cannot be parsed as the value.
+    Parse,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Timeout => write!(f, "timed out"),
+            ReadError::InvalidUtf8 => write!(f, "invalid UTF-8"),
+            ReadError::Parse => write!(f, "cannot parse the input"),
+        }
+    }
+}
+
+impl core::error::Error for ReadError {}
+
+/// A handle of the UART of `inbyte`.
+#[derive(Debug, Copy, Clone)]
+pub struct Stdin {
+    _private: (),
+}
+
+/// This function takes the handle of the UART of `inbyte`.
+///
+/// # Example
+/// ```
+/// use core::time::Duration;
+/// use embeddedsw_rs::{console, println};
+///
+/// let mut buf = [0; 64];
+/// match console::stdin().read_line_timeout(&mut buf, Duration::from_secs(10)) {
+///     Ok(line) => println!("got {}", line),
+///     Err(e) => println!("{}", e),
+/// }
+/// ```
+pub fn stdin() -> Stdin {
+    Stdin { _private: () }
+}
+
+/// The last line ended with CR, so LF just after it is skipped.
+static LAST_CR: AtomicBool = AtomicBool::new(false);
+
+impl Stdin {
+    /// This function takes a received byte if any, without waiting.
+    ///
+    /// With the `semihosting` feature, it always returns `None`,
+    /// because semihosting cannot check the input without waiting.
+    pub fn try_read_byte(&self) -> Option<u8> {
+        #[cfg(feature = "semihosting")]
+        return None;
+        #[cfg(not(feature = "semihosting"))]
+        if read_reg(UART_BASE, UART_SR) & UART_SR_RXEMPTY != 0 {
+            None
+        } else {
+            Some(read_reg(UART_BASE, UART_FIFO) as u8)
+        }
+    }
+
+    /// This function waits for a byte.
+    pub fn read_byte(&self) -> u8 {
+        #[cfg(feature = "semihosting")]
+        return crate::semihosting::read_char();
+        #[cfg(not(feature = "semihosting"))]
+        unsafe {
+            esys::inbyte() as u8
+        }
+    }
+
+    /// This function waits for a byte until the timeout.
+    ///
+    /// With the `semihosting` feature, it ignores the timeout and waits
+    /// for a byte of the host like `read_byte`,
+    /// because semihosting cannot wait for the input with a timeout.
+    ///
+    /// # Errors
+    /// If no byte arrives, it returns `ReadError::Timeout`.
+    pub fn read_byte_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<u8, ReadError> {
+        #[cfg(feature = "semihosting")]
+        {
+            let _ = timeout;
+            Ok(crate::semihosting::read_char())
+        }
+        #[cfg(not(feature = "semihosting"))]
+        {
+            let start = Instant::now();
+            loop {
+                if let Some(b) = self.try_read_byte() {
+                    return Ok(b);
+                }
+                if start.elapsed() >= timeout {
+                    return Err(ReadError::Timeout);
+                }
+            }
+        }
+    }
+
+    /// This function reads a line into the buffer, and takes it without
+    /// the line ending. The line ends with CR, LF or CRLF.
+    ///
+    /// The input is echoed, and backspace and delete erase the last char.
+    /// When the buffer is full, the input is ignored with a bell
+    /// until the line ends.
+    ///
+    /// # Errors
+    /// If the line is not valid UTF-8, it returns `ReadError::InvalidUtf8`.
+    pub fn read_line<'a>(
+        &self,
+        buf: &'a mut [u8],
+    ) -> Result<&'a str, ReadError> {
+        self.read_line_with(buf, || Some(self.read_byte()))
+    }
+
+    /// This function reads a line like `read_line`,
+    /// and gives up when the line does not end before the timeout.
+    ///
+    /// With the `semihosting` feature, it ignores the timeout
+    /// and waits for the line like `read_line`.
+    ///
+    /// # Errors
+    /// If the line does not end, it returns `ReadError::Timeout`,
+    /// and if it is not valid UTF-8, `ReadError::InvalidUtf8`.
+    pub fn read_line_timeout<'a>(
+        &self,
+        buf: &'a mut [u8],
+        timeout: Duration,
+    ) -> Result<&'a str, ReadError> {
+        let start = Instant::now();
+        self.read_line_with(buf, || {
+            self.read_byte_timeout(timeout.saturating_sub(start.elapsed()))
+                .ok()
+        })
+    }
+
+    /// This function reads a line and parses it without surrounding spaces.
+    ///
+    /// # Errors
+    /// If the line cannot be read, it returns the error of `read_line`,
+    /// and if it cannot be parsed, `ReadError::Parse`.
+    ///
+    /// # Example
+    /// ```
+    /// use embeddedsw_rs::{console, print};
+    ///
+    /// print!("gain: ");
+    /// let gain: f32 = console::stdin().read_value(&mut [0; 32]).unwrap();
+    /// ```
+    pub fn read_value<T: FromStr>(
+        &self,
+        buf: &mut [u8],
+    ) -> Result<T, ReadError> {
+        self.read_line(buf)?
+            .trim()
+            .parse()
+            .map_err(|_| ReadError::Parse)
+    }
+
+    fn read_line_with<'a>(
+        &self,
+        buf: &'a mut [u8],
+        mut next: impl FnMut() -> Option<u8>,
+    ) -> Result<&'a str, ReadError> {
+        let mut len = 0;
+        loop {
+            let b = next().ok_or(ReadError::Timeout)?;
+            let last_cr = LAST_CR.swap(false, Ordering::Relaxed);
+            match b {
+                b'\n' if last_cr && len == 0 => {}
+                b'\r' | b'\n' => {
+                    LAST_CR.store(b == b'\r', Ordering::Relaxed);
+                    crate::print!("\n");
+                    break;
+                }
+                BACKSPACE | DELETE => {
+                    if len > 0 {
+                        // erase a whole UTF-8 char.
+                        len -= 1;
+                        while len > 0 && buf[len] & 0xc0 == 0x80 {
+                            len -= 1;
+                        }
+                        crate::print!("\x08 \x08");
+                    }
+                }
+                b if b < 0x20 => {}
+                _ if len == buf.len() => echo(BELL),
+                b => {
+                    buf[len] = b;
+                    len += 1;
+                    echo(b);
+                }
+            }
+        }
+        core::str::from_utf8(&buf[..len]).map_err(|_| ReadError::InvalidUtf8)
+    }
+}
+
+const BELL: u8 = 0x07;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// The input is echoed to the sink of `Stdout`,
+/// which is the host with the `semihosting` feature.
+fn echo(b: u8) {
+    Buffer::new(Sink::Stdout).write_bytes(&[b]);
+}
+
+//-------------------------------------------------------------------------------------------------
+// TX ring
+//-------------------------------------------------------------------------------------------------
+
+struct Ring {
+    buf: [u8; TX_RING_SIZE],
+    head: usize,
+    len: usize,
+}
+
+impl Ring {
+    #[cfg(not(feature = "semihosting"))]
+    fn free(&self) -> usize {
+        TX_RING_SIZE - self.len
+    }
+
+    #[cfg(not(feature = "semihosting"))]
+    fn push(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.buf[(self.head + self.len) % TX_RING_SIZE] = *b;
+            self.len += 1;
+        }
+    }
+
+    fn pop(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let b = self.buf[self.head];
+        self.head = (self.head + 1) % TX_RING_SIZE;
+        self.len -= 1;
+        Some(b)
+    }
+}
+
+/// The ring is only accessed with interrupts masked.
+struct TxRing(UnsafeCell<Ring>);
+
+unsafe impl Sync for TxRing {}
+
+static TX_RING: TxRing = TxRing(UnsafeCell::new(Ring {
+    buf: [0; TX_RING_SIZE],
+    head: 0,
+    len: 0,
+}));
+
+/// This function enables the TX ring buffer,
+/// which the TX-empty interrupt of the UART drains,
+/// so output does not wait for the UART.
+///
+/// It connects the handler to the interrupt of the UART of `outbyte`
+/// and enables it.
+///
+/// # Errors
+/// If the handler cannot be connected, it returns the status.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{console, xscugic::Interrupt};
+///
+/// console::enable_tx_ring(&mut gic, Interrupt::Uart0).unwrap();
+/// ```
+pub fn enable_tx_ring(
+    gic: &mut XScuGic,
+    id: impl InterruptSource + Copy,
+) -> Result<(), XStatus> {
+    gic.connect(id, Some(tx_interrupt_handler))?;
+    gic.enable(id);
+    TX_RING_ENABLED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// This function writes the buffered output synchronously
+/// and disables the TX ring buffer.
+pub fn disable_tx_ring(gic: &mut XScuGic, id: impl InterruptSource + Copy) {
+    panic_flush();
+    TX_RING_ENABLED.store(false, Ordering::Release);
+    write_reg(UART_BASE, UART_IDR, UART_IXR_TXEMPTY);
+    gic.disable(id);
+    gic.disconnect(id);
+}
+
+/// The handler of the UART interrupt, which refills the TX FIFO.
+///
+/// `enable_tx_ring` connects it. Call it from the handler of the UART
+/// if the interrupt is shared with other handlers.
+pub extern "C" fn tx_interrupt_handler() {
+    let isr = read_reg(UART_BASE, UART_ISR);
+    if isr & UART_IXR_TXEMPTY == 0 {
+        return;
+    }
+    write_reg(UART_BASE, UART_ISR, UART_IXR_TXEMPTY);
+    interrupt_free(|| kick(unsafe { &mut *TX_RING.0.get() }));
+}
+
+/// This function fills the TX FIFO from the ring,
+/// and enables the TX-empty interrupt while the ring has data.
+fn kick(ring: &mut Ring) {
+    while !tx_full(UART_BASE) {
+        match ring.pop() {
+            Some(b) => write_reg(UART_BASE, UART_FIFO, b as u32),
+            None => break,
+        }
+    }
+    if ring.len == 0 {
+        write_reg(UART_BASE, UART_IDR, UART_IXR_TXEMPTY);
+    } else {
+        write_reg(UART_BASE, UART_IER, UART_IXR_TXEMPTY);
+    }
+}
+
+/// This function writes all data of the ring by polling.
+fn drain(ring: &mut Ring) {
+    while let Some(b) = ring.pop() {
+        send_byte(UART_BASE, b);
+    }
+}
+
+/// This function writes the output in the TX ring synchronously,
+/// and waits until the UART sends it.
+/// It can be called from the panic handler.
+pub fn panic_flush() {
+    interrupt_free(|| {
+        drain(unsafe { &mut *TX_RING.0.get() });
+        while read_reg(UART_BASE, UART_SR) & UART_IXR_TXEMPTY == 0 {}
+    })
+}
+
+//-------------------------------------------------------------------------------------------------
+// Registers
+//-------------------------------------------------------------------------------------------------
+
+fn read_reg(base: usize, offset: usize) -> u32 {
+    unsafe { ptr::read_volatile((base + offset) as *const u32) }
+}
+
+fn write_reg(base: usize, offset: usize, value: u32) {
+    unsafe { ptr::write_volatile((base + offset) as *mut u32, value) }
+}
+
+fn tx_full(base: usize) -> bool {
+    read_reg(base, UART_SR) & UART_SR_TXFULL != 0
+}
+
+fn send_byte(base: usize, b: u8) {
+    while tx_full(base) {}
+    write_reg(base, UART_FIFO, b as u32);
+}
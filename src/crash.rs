@@ -0,0 +1,486 @@
+use core::{
+    arch::asm,
+    fmt::{self, Write},
+    mem::{size_of, MaybeUninit},
+    ptr::{self, addr_of, addr_of_mut},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+#[cfg(feature = "panic-handler")]
+use core::panic::PanicInfo;
+
+use crate::{
+    cache::{self, CacheRange},
+    println,
+    xscugic::interrupt_free,
+};
+
+#[cfg(feature = "xilffs")]
+use crate::ff::{FResult, Fil};
+
+/// The magic number of a sealed record, "CRSH" in little endian.
+pub const MAGIC_SEALED: u32 = 0x4853_5243;
+/// The magic number of a record which collects log lines, "CRLG".
+pub const MAGIC_RECORDING: u32 = 0x474c_5243;
+/// The version of the record format.
+pub const VERSION: u32 = 1;
+
+/// The maximum length of the message in bytes.
+pub const MESSAGE_LEN: usize = 200;
+/// The number of log lines kept in the ring.
+pub const LOG_LINES: usize = 8;
+/// The maximum length of a log line in bytes.
+pub const LINE_LEN: usize = 60;
+
+/// The offset of the first byte covered by the CRC.
+const CRC_START: usize = 16;
+
+/// The record in the no-init section.
+/// The linker script must place the `.crash_record` section
+/// in OCM or TCM with `NOLOAD`, so it survives a reset.
+///
+/// ```text
+/// .crash_record (NOLOAD) : {
+///    KEEP(*(.crash_record))
+/// } > psu_ocm_ram_0_MEM_0
+/// ```
+#[link_section = ".crash_record"]
+static mut RECORD: MaybeUninit<CrashRecord> = MaybeUninit::uninit();
+
+/// Set when a fault is recorded,
+/// so the panic raised after the fault does not overwrite the record.
+static FAULT_RECORDED: AtomicBool = AtomicBool::new(false);
+
+//-------------------------------------------------------------------------------------------------
+// CrashKind
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the causes of a crash.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CrashKind {
+    Panic = 1,
+    /// An abort or an undefined instruction recorded by a handler.
+    Fault = 2,
+    /// A crash recorded by the application.
+    User = 3,
+}
+
+impl CrashKind {
+    fn from_u32(kind: u32) -> Option<Self> {
+        match kind {
+            1 => Some(CrashKind::Panic),
+            2 => Some(CrashKind::Fault),
+            3 => Some(CrashKind::User),
+            _ => None,
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// CrashRecord
+//-------------------------------------------------------------------------------------------------
+
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct LogLine {
+    len: u32,
+    text: [u8; LINE_LEN],
+}
+
+/// A post-mortem record of a crash.
+///
+/// The layout is fixed, and all words are little endian.
+/// `tools/crashdump` decodes it on the host.
+///
+/// | offset | field                                          |
+/// |--------|------------------------------------------------|
+/// | 0      | magic                                          |
+/// | 4      | version                                        |
+/// | 8      | size of the record                             |
+/// | 12     | CRC-32 of the bytes from offset 16 to the end  |
+/// | 16     | kind                                           |
+/// | 20     | LR, SP, CPSR, DFSR, DFAR, IFSR, IFAR           |
+/// | 48     | length of the message                          |
+/// | 52     | message (200 bytes)                            |
+/// | 252    | number of log lines written                    |
+/// | 256    | log lines (8 x (length, 60 bytes))             |
+#[repr(C, align(32))]
+#[derive(Copy, Clone)]
+pub struct CrashRecord {
+    magic: u32,
+    version: u32,
+    size: u32,
+    crc: u32,
+    kind: u32,
+    lr: u32,
+    sp: u32,
+    cpsr: u32,
+    dfsr: u32,
+    dfar: u32,
+    ifsr: u32,
+    ifar: u32,
+    message_len: u32,
+    message: [u8; MESSAGE_LEN],
+    log_head: u32,
+    log: [LogLine; LOG_LINES],
+}
+
+const _: () = assert!(size_of::<CrashRecord>() == 768);
+
+impl CrashRecord {
+    /// This function takes the cause of the crash.
+    pub fn kind(&self) -> Option<CrashKind> {
+        CrashKind::from_u32(self.kind)
+    }
+
+    /// This function takes the link register when the crash was recorded.
+    pub fn lr(&self) -> u32 {
+        self.lr
+    }
+
+    /// This function takes the stack pointer when the crash was recorded.
+    pub fn sp(&self) -> u32 {
+        self.sp
+    }
+
+    /// This function takes CPSR when the crash was recorded.
+    pub fn cpsr(&self) -> u32 {
+        self.cpsr
+    }
+
+    /// This function takes the data fault status and address registers.
+    pub fn data_fault(&self) -> (u32, u32) {
+        (self.dfsr, self.dfar)
+    }
+
+    /// This function takes the instruction fault status and address registers.
+    pub fn instruction_fault(&self) -> (u32, u32) {
+        (self.ifsr, self.ifar)
+    }
+
+    /// This function takes the message, which may be truncated.
+    pub fn message(&self) -> &str {
+        text(&self.message, self.message_len)
+    }
+
+    /// This function iterates the log lines from the oldest one.
+    pub fn log_lines(&self) -> impl Iterator<Item = &str> {
+        let head = self.log_head as usize;
+        let count = head.min(LOG_LINES);
+        (head - count..head).map(move |i| {
+            let line = &self.log[i % LOG_LINES];
+            text(&line.text, line.len)
+        })
+    }
+
+    /// This function takes the raw bytes, which `tools/crashdump` decodes.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(
+                self as *const Self as *const u8,
+                size_of::<Self>(),
+            )
+        }
+    }
+
+    /// This function checks the magic number, the version and the CRC.
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC_SEALED
+            && self.version == VERSION
+            && self.size as usize == size_of::<Self>()
+            && self.crc == crc32(&self.as_bytes()[CRC_START..])
+    }
+
+    /// This function prints the record to the UART.
+    pub fn print(&self) {
+        match self.kind() {
+            Some(kind) => println!("crash record: {:?}", kind),
+            None => println!("crash record: unknown kind {}", self.kind),
+        }
+        println!("{}", self.message());
+        println!(
+            "LR 0x{:08x} SP 0x{:08x} CPSR 0x{:08x}",
+            self.lr, self.sp, self.cpsr
+        );
+        println!(
+            "DFSR 0x{:08x} DFAR 0x{:08x} IFSR 0x{:08x} IFAR 0x{:08x}",
+            self.dfsr, self.dfar, self.ifsr, self.ifar
+        );
+        for line in self.log_lines() {
+            println!("| {}", line);
+        }
+    }
+
+    /// This function writes the raw bytes to the file,
+    /// so `tools/crashdump` can decode it on the host.
+    ///
+    /// # Errors
+    /// If the file cannot be written, it returns the FResult.
+    #[cfg(feature = "xilffs")]
+    pub fn write_to(&self, fil: &mut Fil) -> Result<(), FResult> {
+        let bytes = self.as_bytes();
+        let mut written = 0;
+        while written < bytes.len() {
+            let n = fil.write(&bytes[written..], bytes.len() - written)?;
+            if n == 0 {
+                return Err(FResult::FDenied);
+            }
+            written += n;
+        }
+        Ok(())
+    }
+}
+
+fn text(bytes: &[u8], len: u32) -> &str {
+    let bytes = &bytes[..(len as usize).min(bytes.len())];
+    match core::str::from_utf8(bytes) {
+        Ok(s) => s,
+        // the writer truncates at char boundaries, so it is corrupted.
+        Err(e) => unsafe {
+            core::str::from_utf8_unchecked(&bytes[..e.valid_up_to()])
+        },
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Next boot
+//-------------------------------------------------------------------------------------------------
+
+/// This function takes the record of the crash before the last reset,
+/// and starts collecting log lines of this boot.
+///
+/// Call it at the beginning of main, because log lines are not collected
+/// while the record of the last crash is kept.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::crash;
+///
+/// if let Some(record) = crash::take() {
+///     record.print();
+/// }
+/// crash::log(format_args!("boot"));
+/// ```
+pub fn take() -> Option<CrashRecord> {
+    interrupt_free(|| unsafe {
+        let record = ptr::read_volatile(addr_of!(RECORD) as *const CrashRecord);
+        reset(record_ptr());
+        if record.is_valid() {
+            Some(record)
+        } else {
+            None
+        }
+    })
+}
+
+/// This function discards the record and the log lines.
+pub fn clear() {
+    interrupt_free(|| unsafe { reset(record_ptr()) })
+}
+
+fn record_ptr() -> *mut CrashRecord {
+    addr_of_mut!(RECORD) as *mut CrashRecord
+}
+
+/// This function starts collecting log lines into the record.
+unsafe fn reset(record: *mut CrashRecord) {
+    addr_of_mut!((*record).log_head).write_volatile(0);
+    addr_of_mut!((*record).magic).write_volatile(MAGIC_RECORDING);
+}
+
+//-------------------------------------------------------------------------------------------------
+// Log lines
+//-------------------------------------------------------------------------------------------------
+
+/// Append a line to the log ring of the crash record.
+#[macro_export]
+macro_rules! crash_log {
+    ($($arg:tt)*) => ($crate::crash::log(format_args!($($arg)*)));
+}
+
+/// This function appends a line to the log ring,
+/// and the oldest line is overwritten when the ring is full.
+/// The line is truncated to `LINE_LEN` bytes.
+///
+/// It does nothing while the record of the last crash is kept,
+/// that is, until `take` or `clear` is called.
+pub fn log(args: fmt::Arguments) {
+    let mut line = LogLine {
+        len: 0,
+        text: [0; LINE_LEN],
+    };
+    let mut w = Truncating::new(&mut line.text);
+    let _ = w.write_fmt(args);
+    line.len = w.len as u32;
+
+    interrupt_free(|| unsafe {
+        let record = record_ptr();
+        if addr_of!((*record).magic).read_volatile() != MAGIC_RECORDING {
+            return;
+        }
+        let head = addr_of!((*record).log_head).read_volatile();
+        addr_of_mut!((*record).log[head as usize % LOG_LINES])
+            .write_volatile(line);
+        addr_of_mut!((*record).log_head).write_volatile(head.wrapping_add(1));
+    })
+}
+
+/// The writer which truncates the text at a char boundary.
+struct Truncating<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Truncating<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+}
+
+impl Write for Truncating<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let room = self.buf.len() - self.len;
+        let mut n = s.len().min(room);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        if n < s.len() {
+            Err(fmt::Error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Recording
+//-------------------------------------------------------------------------------------------------
+
+/// This function records a crash with the message,
+/// and writes the record back to memory so it survives a reset.
+///
+/// The log lines of this boot are kept in the record.
+/// An unread record of the last crash is overwritten.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::crash::{self, CrashKind};
+///
+/// crash::record(CrashKind::User, format_args!("watchdog: task stalled"));
+/// ```
+#[inline(never)]
+pub fn record(kind: CrashKind, args: fmt::Arguments) {
+    let lr: u32;
+    let sp: u32;
+    unsafe {
+        asm!("mov {}, lr", out(reg) lr, options(nomem, nostack));
+        asm!("mov {}, sp", out(reg) sp, options(nomem, nostack));
+    }
+    interrupt_free(|| unsafe { seal(kind, lr, sp, args) })
+}
+
+/// This function records a data abort. The data abort handler calls it
+/// with the address of the faulting instruction and the SP of the faulting
+/// mode.
+pub(crate) fn record_fault(lr: u32, sp: u32, args: fmt::Arguments) {
+    interrupt_free(|| unsafe { seal(CrashKind::Fault, lr, sp, args) });
+    FAULT_RECORDED.store(true, Ordering::Release);
+}
+
+/// This function records a panic. The panic handler calls it.
+/// A panic raised by the data abort handler keeps the record of the fault.
+#[cfg(feature = "panic-handler")]
+pub(crate) fn record_panic(info: &PanicInfo, lr: u32, sp: u32) {
+    if FAULT_RECORDED.load(Ordering::Acquire) {
+        return;
+    }
+    let message = info.message();
+    unsafe {
+        match info.location() {
+            Some(l) => seal(
+                CrashKind::Panic,
+                lr,
+                sp,
+                format_args!("{} at {}:{}", message, l.file(), l.line()),
+            ),
+            None => seal(CrashKind::Panic, lr, sp, format_args!("{}", message)),
+        }
+    }
+}
+
+/// # Safety
+/// Interrupts must be masked.
+unsafe fn seal(kind: CrashKind, lr: u32, sp: u32, args: fmt::Arguments) {
+    let record = record_ptr();
+    if addr_of!((*record).magic).read_volatile() != MAGIC_RECORDING {
+        // the log lines are of the last boot or garbage.
+        reset(record);
+    }
+
+    let (cpsr, dfsr, dfar, ifsr, ifar): (u32, u32, u32, u32, u32);
+    asm!("mrs {}, cpsr", out(reg) cpsr, options(nomem, nostack));
+    asm!("mrc p15, 0, {}, c5, c0, 0", out(reg) dfsr, options(nomem, nostack));
+    asm!("mrc p15, 0, {}, c6, c0, 0", out(reg) dfar, options(nomem, nostack));
+    asm!("mrc p15, 0, {}, c5, c0, 1", out(reg) ifsr, options(nomem, nostack));
+    asm!("mrc p15, 0, {}, c6, c0, 2", out(reg) ifar, options(nomem, nostack));
+
+    let r = &mut *record;
+    r.version = VERSION;
+    r.size = size_of::<CrashRecord>() as u32;
+    r.kind = kind as u32;
+    r.lr = lr;
+    r.sp = sp;
+    r.cpsr = cpsr;
+    r.dfsr = dfsr;
+    r.dfar = dfar;
+    r.ifsr = ifsr;
+    r.ifar = ifar;
+    r.message = [0; MESSAGE_LEN];
+    let mut w = Truncating::new(&mut r.message);
+    let _ = w.write_fmt(args);
+    r.message_len = w.len as u32;
+    for line in r.log.iter_mut() {
+        line.len = line.len.min(LINE_LEN as u32);
+    }
+    r.crc = crc32(&r.as_bytes()[CRC_START..]);
+    ptr::write_volatile(addr_of_mut!(r.magic), MAGIC_SEALED);
+
+    // a reset does not write back the D-cache.
+    cache::dcache_flush_range(CacheRange::from_ref(r));
+}
+
+//-------------------------------------------------------------------------------------------------
+// CRC-32
+//-------------------------------------------------------------------------------------------------
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+/// This function computes the CRC-32 (IEEE 802.3) of the bytes.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |c, b| {
+        CRC_TABLE[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
@@ -0,0 +1,221 @@
+extern crate embeddedsw_sys;
+use core::sync::atomic::{AtomicBool, Ordering};
+use embeddedsw_sys as esys;
+
+use crate::xscugic::{mfcpsr, mtcpsr};
+
+#[cfg(feature = "defmt-ring")]
+pub use ring::{Ring, DEFMT_RING, RING_SIZE};
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+static mut CPSR: u32 = 0;
+static mut ENCODER: defmt::Encoder = defmt::Encoder::new();
+
+//-------------------------------------------------------------------------------------------------
+// Global logger
+//-------------------------------------------------------------------------------------------------
+
+/// The global logger of `defmt`.
+///
+/// Frames are encoded with rzCOBS and sent to the UART of `outbyte`,
+/// or written to `DEFMT_RING` with the `defmt-ring` feature.
+/// IRQ and FIQ are masked while a frame is written,
+/// so frames from interrupt handlers are not interleaved.
+///
+/// The firmware must be linked with `-C link-arg=-Tdefmt.x`.
+/// With the UART transport, do not print text to the same UART,
+/// because the host decoder cannot tell it from frames.
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {
+        unsafe {
+            let cpsr = mfcpsr();
+            mtcpsr(cpsr | esys::XIL_EXCEPTION_ALL);
+            if TAKEN.swap(true, Ordering::Acquire) {
+                panic!("defmt logger is acquired recursively");
+            }
+            CPSR = cpsr;
+            #[cfg(feature = "defmt-ring")]
+            ring::start_frame();
+            (*core::ptr::addr_of_mut!(ENCODER)).start_frame(transport_write);
+        }
+    }
+
+    unsafe fn flush() {
+        #[cfg(feature = "defmt-ring")]
+        ring::publish();
+    }
+
+    unsafe fn release() {
+        (*core::ptr::addr_of_mut!(ENCODER)).end_frame(transport_write);
+        #[cfg(feature = "defmt-ring")]
+        ring::publish();
+        let cpsr = CPSR;
+        TAKEN.store(false, Ordering::Release);
+        mtcpsr(
+            (mfcpsr() & !esys::XIL_EXCEPTION_ALL)
+                | (cpsr & esys::XIL_EXCEPTION_ALL),
+        );
+    }
+
+    unsafe fn write(bytes: &[u8]) {
+        (*core::ptr::addr_of_mut!(ENCODER)).write(bytes, transport_write);
+    }
+}
+
+#[cfg(not(feature = "defmt-ring"))]
+fn transport_write(bytes: &[u8]) {
+    for b in bytes {
+        unsafe { esys::outbyte(*b as _) };
+    }
+}
+
+#[cfg(feature = "defmt-ring")]
+fn transport_write(bytes: &[u8]) {
+    unsafe { ring::write(bytes) }
+}
+
+#[cfg(feature = "defmt-timestamp")]
+defmt::timestamp!(
+    "{=u64:us}",
+    crate::xtime::Instant::now().since_boot().as_micros() as u64
+);
+
+//-------------------------------------------------------------------------------------------------
+// Ring buffer
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "defmt-ring")]
+mod ring {
+    use core::ptr::{addr_of, addr_of_mut};
+
+    use crate::cache::{self, CacheRange, CACHE_LINE_SIZE};
+
+    /// The size of the data area of the ring buffer.
+    pub const RING_SIZE: usize = 4096;
+
+    /// A ring buffer which a debugger or the APU drains.
+    ///
+    /// The producer owns `write` and the consumer owns `read`,
+    /// and the data is in `buf[read..write]` modulo the size.
+    /// When a frame does not fit in the buffer, the whole frame is dropped
+    /// and counted in `dropped`, so the decoder never sees a partial frame.
+    ///
+    /// The buffer is written back from the D-cache after each frame,
+    /// and `read` is in its own cache line, which is invalidated before use.
+    #[repr(C, align(32))]
+    pub struct Ring {
+        /// "DEFMT RING" padded with zeros, to find the buffer in dumps.
+        pub id: [u8; 16],
+        pub size: u32,
+        pub write: u32,
+        pub dropped: u32,
+        _reserved: u32,
+        pub read: ReadIndex,
+        pub buf: [u8; RING_SIZE],
+    }
+
+    /// The read index owned by the consumer.
+    #[repr(C, align(32))]
+    pub struct ReadIndex {
+        pub read: u32,
+        _reserved: [u32; 7],
+    }
+
+    /// The ring buffer, which the linker script can place in OCM
+    /// with the `.defmt_ring` section to share it with the APU.
+    #[no_mangle]
+    #[link_section = ".defmt_ring"]
+    pub static mut DEFMT_RING: Ring = Ring {
+        id: *b"DEFMT RING\0\0\0\0\0\0",
+        size: RING_SIZE as u32,
+        write: 0,
+        dropped: 0,
+        _reserved: 0,
+        read: ReadIndex {
+            read: 0,
+            _reserved: [0; 7],
+        },
+        buf: [0; RING_SIZE],
+    };
+
+    /// The write index when the frame started, which is not published yet.
+    static mut START: u32 = 0;
+    static mut WRITE: u32 = 0;
+    /// Whether the frame is dropped because it does not fit.
+    static mut OVERFLOW: bool = false;
+
+    fn ring() -> *mut Ring {
+        addr_of_mut!(DEFMT_RING)
+    }
+
+    /// # Safety
+    /// The logger must be acquired.
+    pub unsafe fn start_frame() {
+        WRITE = addr_of!((*ring()).write).read_volatile();
+        START = WRITE;
+        OVERFLOW = false;
+    }
+
+    /// # Safety
+    /// The logger must be acquired.
+    pub unsafe fn write(bytes: &[u8]) {
+        if OVERFLOW {
+            return;
+        }
+        let ring = ring();
+        let read_line = addr_of!((*ring).read) as usize;
+        // the line is only written by the consumer.
+        cache::dcache_invalidate_line(read_line);
+        let read = addr_of!((*ring).read.read).read_volatile() as usize;
+
+        let mut write = WRITE as usize;
+        let used = (write + RING_SIZE - read % RING_SIZE) % RING_SIZE;
+        let free = RING_SIZE - 1 - used;
+        if bytes.len() > free {
+            // the frame is dropped from its start.
+            OVERFLOW = true;
+            WRITE = START;
+            let dropped = addr_of_mut!((*ring).dropped);
+            dropped.write_volatile(dropped.read_volatile().wrapping_add(1));
+            return;
+        }
+        for b in bytes {
+            addr_of_mut!((*ring).buf[write]).write_volatile(*b);
+            write = (write + 1) % RING_SIZE;
+        }
+        WRITE = write as u32;
+    }
+
+    /// This function writes back the data of the frame,
+    /// and then publishes the write index.
+    ///
+    /// # Safety
+    /// The logger must be acquired.
+    pub unsafe fn publish() {
+        let ring = ring();
+        let buf = addr_of!((*ring).buf) as usize;
+        let (start, end) = (START as usize, WRITE as usize);
+        if start <= end {
+            flush(buf + start, end - start);
+        } else {
+            flush(buf + start, RING_SIZE - start);
+            flush(buf, end);
+        }
+        addr_of_mut!((*ring).write).write_volatile(WRITE);
+        cache::dcache_flush_line(ring as usize);
+        START = WRITE;
+    }
+
+    fn flush(addr: usize, len: usize) {
+        if len > 0 {
+            cache::dcache_flush_range(
+                CacheRange::new(addr, len).expand_to_lines(),
+            );
+        }
+    }
+
+    const _: () = assert!(core::mem::size_of::<ReadIndex>() == CACHE_LINE_SIZE);
+}
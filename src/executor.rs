@@ -0,0 +1,613 @@
+#[cfg(test)]
+use crate::host::{interrupt_free, wait_for_interrupt, Mutex};
+#[cfg(not(test))]
+use crate::xscugic::{interrupt_free, wait_for_interrupt, Mutex};
+use core::{
+    future::Future,
+    pin::{pin, Pin},
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Executor
+//-------------------------------------------------------------------------------------------------
+
+/// The maximum number of tasks of an executor.
+pub const MAX_TASKS: usize = 31;
+
+/// Bit flags of the tasks which have been woken.
+/// Bit 31 is shared with `block_on`.
+static READY: AtomicU32 = AtomicU32::new(0);
+
+/// The error returned by `Executor::spawn`
+#[derive(Debug)]
+pub enum SpawnError {
+    /// All task slots are used.
+    Full,
+}
+
+/// A `no_std` executor which runs tasks woken by interrupt handlers.
+///
+/// Tasks are pinned by the caller, so the executor does not allocate.
+/// When no task is ready, the processor sleeps with `wfi`
+/// until an interrupt handler wakes a task.
+/// Only one executor (or `block_on`) can run at a time on each core.
+///
+/// # Example
+/// ```
+/// use core::pin::pin;
+/// use embeddedsw_rs::executor::{Executor, Signal, Timer};
+///
+/// static BUTTON: Signal = Signal::new();
+///
+/// async fn blink() {
+///     loop {
+///         Timer::after(500).await;
+///         println!("blink");
+///     }
+/// }
+///
+/// async fn button() {
+///     loop {
+///         BUTTON.wait().await;
+///         println!("pressed");
+///     }
+/// }
+///
+/// // the GPIO handler connected by XScuGic::connect
+/// extern "C" fn gpio_handler() {
+///     BUTTON.signal();
+/// }
+///
+/// // the TTC handler connected by XScuGic::connect
+/// extern "C" fn ttc_handler() {
+///     embeddedsw_rs::executor::tick();
+/// }
+///
+/// let mut blink = pin!(blink());
+/// let mut button = pin!(button());
+/// let mut executor = Executor::<2>::new();
+/// executor.spawn(blink.as_mut()).unwrap();
+/// executor.spawn(button.as_mut()).unwrap();
+/// executor.run();
+/// ```
+pub struct Executor<'a, const N: usize> {
+    tasks: [Option<Pin<&'a mut dyn Future<Output = ()>>>; N],
+}
+
+impl<'a, const N: usize> Executor<'a, N> {
+    /// This function creates an executor which has N task slots.
+    pub fn new() -> Self {
+        const { assert!(N <= MAX_TASKS, "too many task slots") };
+
+        Self {
+            tasks: core::array::from_fn(|_| None),
+        }
+    }
+
+    /// This function adds a task to the executor.
+    /// The task is polled at the next `poll` or `run`.
+    ///
+    /// # Errors
+    /// If all task slots are used, it returns `SpawnError::Full`.
+    pub fn spawn(
+        &mut self,
+        task: Pin<&'a mut dyn Future<Output = ()>>,
+    ) -> Result<(), SpawnError> {
+        let (i, slot) = self
+            .tasks
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+            .ok_or(SpawnError::Full)?;
+
+        *slot = Some(task);
+        READY.fetch_or(1 << i, Ordering::Release);
+        Ok(())
+    }
+
+    /// This function polls every task which has been woken once.
+    ///
+    /// It returns true if all tasks have completed.
+    /// It does not sleep, so it can be used to drive the executor
+    /// step by step, for example after simulating an interrupt
+    /// by calling `Signal::signal`.
+    pub fn poll(&mut self) -> bool {
+        let ready = READY.fetch_and(BLOCK_ON_BIT, Ordering::Acquire);
+
+        for (i, slot) in self.tasks.iter_mut().enumerate() {
+            if ready & (1 << i) == 0 {
+                continue;
+            }
+
+            if let Some(task) = slot {
+                let waker = task_waker(i);
+                let mut cx = Context::from_waker(&waker);
+                if task.as_mut().poll(&mut cx).is_ready() {
+                    *slot = None;
+                }
+            }
+        }
+
+        self.tasks.iter().all(Option::is_none)
+    }
+
+    /// This function runs tasks until all of them complete.
+    /// While no task is ready, the processor waits for interrupts.
+    pub fn run(&mut self) {
+        while !self.poll() {
+            interrupt_free(|| {
+                if READY.load(Ordering::Acquire) & !BLOCK_ON_BIT == 0 {
+                    wait_for_interrupt();
+                }
+            });
+        }
+    }
+}
+
+impl<const N: usize> Default for Executor<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// This function runs a future to completion on the current core.
+/// While the future is pending, the processor waits for interrupts.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = task_waker(BLOCK_ON_INDEX);
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        READY.fetch_and(!BLOCK_ON_BIT, Ordering::Acquire);
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        interrupt_free(|| {
+            if READY.load(Ordering::Acquire) & BLOCK_ON_BIT == 0 {
+                wait_for_interrupt();
+            }
+        });
+    }
+}
+
+const BLOCK_ON_INDEX: usize = MAX_TASKS;
+const BLOCK_ON_BIT: u32 = 1 << BLOCK_ON_INDEX;
+
+static VTABLE: RawWakerVTable =
+    RawWakerVTable::new(waker_clone, waker_wake, waker_wake, waker_drop);
+
+fn task_waker(index: usize) -> Waker {
+    unsafe { Waker::from_raw(RawWaker::new(index as *const (), &VTABLE)) }
+}
+
+unsafe fn waker_clone(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &VTABLE)
+}
+
+unsafe fn waker_wake(data: *const ()) {
+    READY.fetch_or(1 << data as usize, Ordering::Release);
+}
+
+unsafe fn waker_drop(_data: *const ()) {}
+
+//-------------------------------------------------------------------------------------------------
+// Signal
+//-------------------------------------------------------------------------------------------------
+
+/// An event which an interrupt handler signals to a task.
+///
+/// `signal` can be called from interrupt handlers,
+/// and `wait` resolves after it is called.
+/// Signals which are sent while no task is waiting are not lost,
+/// but several signals are merged into one.
+pub struct Signal {
+    signaled: AtomicBool,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Signal {
+    /// This function creates a signal.
+    pub const fn new() -> Self {
+        Self {
+            signaled: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        }
+    }
+
+    /// This function signals the event and wakes the waiting task.
+    pub fn signal(&self) {
+        self.signaled.store(true, Ordering::Release);
+        if let Some(waker) = self.waker.lock(|waker| waker.take()) {
+            waker.wake();
+        }
+    }
+
+    /// This function clears the event which has not been waited yet.
+    pub fn reset(&self) {
+        self.signaled.store(false, Ordering::Release);
+    }
+
+    /// This function checks whether the event has been signaled.
+    pub fn is_signaled(&self) -> bool {
+        self.signaled.load(Ordering::Acquire)
+    }
+
+    /// This function returns a future which resolves
+    /// when the event is signaled.
+    pub fn wait(&self) -> SignalFuture<'_> {
+        SignalFuture { signal: self }
+    }
+}
+
+impl Default for Signal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The future returned by `Signal::wait`.
+pub struct SignalFuture<'a> {
+    signal: &'a Signal,
+}
+
+impl Future for SignalFuture<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let signal = self.signal;
+
+        // register the waker first so that a signal between
+        // the check and the registration is not lost.
+        signal.waker.lock(|waker| *waker = Some(cx.waker().clone()));
+
+        if signal.signaled.swap(false, Ordering::Acquire) {
+            signal.waker.lock(|waker| *waker = None);
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Timer
+//-------------------------------------------------------------------------------------------------
+
+/// The maximum number of timers which wait at the same time.
+/// If more timers wait, the extra ones are polled on every tick.
+pub const MAX_TIMERS: usize = 8;
+
+static TICKS: AtomicU32 = AtomicU32::new(0);
+
+/// A slot of a waiting timer, which the timer owns until it is dropped.
+struct TimerSlot {
+    deadline: u32,
+    /// The waker of the task, which is taken when the timer expires.
+    waker: Option<Waker>,
+}
+
+static TIMERS: Mutex<[Option<TimerSlot>; MAX_TIMERS]> =
+    Mutex::new([const { None }; MAX_TIMERS]);
+
+/// Wakers of the timers which did not get a slot in `TIMERS`.
+static OVERFLOW_TIMERS: AtomicU32 = AtomicU32::new(0);
+
+/// This function advances the tick count and wakes the expired timers.
+///
+/// Call it from a periodic interrupt handler, such as a TTC interval
+/// interrupt. The tick period defines the unit of `Timer::after`.
+pub fn tick() {
+    let now = TICKS.fetch_add(1, Ordering::AcqRel).wrapping_add(1);
+
+    TIMERS.lock(|timers| {
+        for slot in timers.iter_mut().flatten() {
+            if expired(now, slot.deadline) {
+                if let Some(waker) = slot.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    });
+
+    let overflow = OVERFLOW_TIMERS.swap(0, Ordering::AcqRel);
+    if overflow != 0 {
+        READY.fetch_or(overflow, Ordering::Release);
+    }
+}
+
+/// This function takes the current tick count.
+pub fn now() -> u32 {
+    TICKS.load(Ordering::Acquire)
+}
+
+#[inline(always)]
+fn expired(now: u32, deadline: u32) -> bool {
+    now.wrapping_sub(deadline) as i32 >= 0
+}
+
+/// A future which resolves after a number of ticks.
+///
+/// Each waiting timer has its own slot in the timer table,
+/// so several timers can wait in one task, such as in a select.
+/// The slot is released when the timer expires or is dropped.
+pub struct Timer {
+    deadline: u32,
+    /// The index of the slot in `TIMERS` while the timer waits.
+    slot: Option<usize>,
+}
+
+impl Timer {
+    /// This function creates a timer which expires after the ticks.
+    pub fn after(ticks: u32) -> Self {
+        Self::at(now().wrapping_add(ticks))
+    }
+
+    /// This function creates a timer which expires at the tick count.
+    pub fn at(deadline: u32) -> Self {
+        Self {
+            deadline,
+            slot: None,
+        }
+    }
+
+    fn release(&mut self) {
+        if let Some(i) = self.slot.take() {
+            TIMERS.lock(|timers| timers[i] = None);
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if expired(now(), this.deadline) {
+            this.release();
+            return Poll::Ready(());
+        }
+
+        let deadline = this.deadline;
+        let slot = this.slot;
+        this.slot = TIMERS.lock(|timers| {
+            let i = match slot {
+                Some(i) => i,
+                None => timers.iter().position(Option::is_none)?,
+            };
+            timers[i] = Some(TimerSlot {
+                deadline,
+                waker: Some(cx.waker().clone()),
+            });
+            Some(i)
+        });
+
+        if this.slot.is_none() {
+            // no slot is left, so poll again on the next tick.
+            // Only the tasks of `Executor` can wait in this way.
+            let waker = cx.waker();
+            if core::ptr::eq(waker.vtable(), &VTABLE) {
+                OVERFLOW_TIMERS
+                    .fetch_or(1 << waker.data() as usize, Ordering::AcqRel);
+            } else {
+                cx.waker().wake_by_ref();
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Channel
+//-------------------------------------------------------------------------------------------------
+
+/// A single-producer single-consumer byte channel
+/// from an interrupt handler to a task, such as UART RX.
+///
+/// The interrupt handler pushes received bytes with `push`,
+/// and the task receives them with `recv().await`.
+///
+/// # Example
+/// ```
+/// static UART_RX: Channel<64> = Channel::new();
+///
+/// // the UART handler connected by XScuGic::connect
+/// extern "C" fn uart_handler() {
+///     while let Some(byte) = read_rx_fifo() {
+///         UART_RX.push(byte);
+///     }
+/// }
+///
+/// async fn shell() {
+///     loop {
+///         let byte = UART_RX.recv().await;
+///         print!("{}", byte as char);
+///     }
+/// }
+/// ```
+pub struct Channel<const N: usize> {
+    buf: Mutex<Ring<N>>,
+    signal: Signal,
+}
+
+struct Ring<const N: usize> {
+    buf: [u8; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> Channel<N> {
+    /// This function creates a channel which buffers N bytes.
+    pub const fn new() -> Self {
+        Self {
+            buf: Mutex::new(Ring {
+                buf: [0; N],
+                head: 0,
+                len: 0,
+            }),
+            signal: Signal::new(),
+        }
+    }
+
+    /// This function pushes a byte and wakes the receiving task.
+    ///
+    /// It returns false if the buffer is full and the byte is dropped.
+    pub fn push(&self, byte: u8) -> bool {
+        let pushed = self.buf.lock(|ring| {
+            if ring.len == N {
+                return false;
+            }
+            ring.buf[(ring.head + ring.len) % N] = byte;
+            ring.len += 1;
+            true
+        });
+
+        self.signal.signal();
+        pushed
+    }
+
+    /// This function pops a byte without waiting.
+    pub fn try_recv(&self) -> Option<u8> {
+        self.buf.lock(|ring| {
+            if ring.len == 0 {
+                return None;
+            }
+            let byte = ring.buf[ring.head];
+            ring.head = (ring.head + 1) % N;
+            ring.len -= 1;
+            Some(byte)
+        })
+    }
+
+    /// This function waits until a byte is pushed and pops it.
+    pub async fn recv(&self) -> u8 {
+        loop {
+            if let Some(byte) = self.try_recv() {
+                return byte;
+            }
+            self.signal.wait().await;
+        }
+    }
+}
+
+impl<const N: usize> Default for Channel<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// DMA
+//-------------------------------------------------------------------------------------------------
+
+/// This function waits until the DMA transfer completes.
+///
+/// The signal must be signaled by the DMA interrupt handler
+/// after acknowledging the interrupt with `XAxiDma::irq_ack`.
+#[cfg(all(feature = "xaxidma", not(test)))]
+pub async fn dma_done<T, A>(
+    transfer: &mut crate::xaxidma::DmaTransfer<'_, T, A>,
+    signal: &Signal,
+) where
+    T: crate::xaxidma::DmaData,
+    A: core::alloc::Allocator,
+{
+    while !transfer.is_done() {
+        signal.wait().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::future::poll_fn;
+
+    /// The executor uses global state, so the tests run one by one.
+    static SERIAL: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn serial() -> std::sync::MutexGuard<'static, ()> {
+        let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        READY.store(0, Ordering::Release);
+        OVERFLOW_TIMERS.store(0, Ordering::Release);
+        TIMERS.lock(|timers| timers.iter_mut().for_each(|t| *t = None));
+        guard
+    }
+
+    #[test]
+    fn spawn_runs_tasks_until_full() {
+        let _guard = serial();
+        let mut a = pin!(async {});
+        let mut b = pin!(async {});
+        let mut c = pin!(async {});
+        let mut executor = Executor::<2>::new();
+        executor.spawn(a.as_mut()).unwrap();
+        executor.spawn(b.as_mut()).unwrap();
+        assert!(matches!(executor.spawn(c.as_mut()), Err(SpawnError::Full)));
+        assert!(executor.poll());
+    }
+
+    #[test]
+    fn signal_wakes_waiting_task() {
+        let _guard = serial();
+        static EVENT: Signal = Signal::new();
+        let mut task = pin!(EVENT.wait());
+        let mut executor = Executor::<1>::new();
+        executor.spawn(task.as_mut()).unwrap();
+
+        assert!(!executor.poll());
+        // nothing is woken, so the task is not polled.
+        assert!(!executor.poll());
+        EVENT.signal();
+        assert!(executor.poll());
+    }
+
+    #[test]
+    fn timers_in_one_task_keep_their_deadlines() {
+        let _guard = serial();
+        let start = now();
+        let mut short = Timer::after(2);
+        let mut long = Timer::after(5);
+        let mut fired = [None, None];
+        let mut task = pin!(poll_fn(|cx| {
+            if fired[0].is_none() && Pin::new(&mut short).poll(cx).is_ready() {
+                fired[0] = Some(now().wrapping_sub(start));
+            }
+            if fired[1].is_none() && Pin::new(&mut long).poll(cx).is_ready() {
+                fired[1] = Some(now().wrapping_sub(start));
+            }
+            match fired {
+                [Some(_), Some(_)] => Poll::Ready(()),
+                _ => Poll::Pending,
+            }
+        }));
+        let mut executor = Executor::<1>::new();
+        executor.spawn(task.as_mut()).unwrap();
+
+        while !executor.poll() {
+            tick();
+        }
+        assert_eq!(fired, [Some(2), Some(5)]);
+    }
+
+    #[test]
+    fn dropped_timers_release_slots() {
+        let _guard = serial();
+        let waker = task_waker(0);
+        let mut cx = Context::from_waker(&waker);
+        for _ in 0..MAX_TIMERS * 2 {
+            let mut timer = pin!(Timer::after(10));
+            assert!(timer.as_mut().poll(&mut cx).is_pending());
+            assert!(timer.slot.is_some());
+        }
+        TIMERS.lock(|timers| assert!(timers.iter().all(Option::is_none)));
+    }
+}
@@ -40,9 +40,8 @@ pub enum FileMountOption {
 /// Enumerates for Return values.
 /// Please see [Fatfs library](http://elm-chan.org/fsw/ff/doc/rc.html#de) to get more information.
 #[repr(u32)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FResult {
-    FOk = FRESULT::FR_OK as u32,
     FDiskErr = FRESULT::FR_DISK_ERR as u32,
     FIntErr = FRESULT::FR_INT_ERR as u32,
     FNotReady = FRESULT::FR_NOT_READY as u32,
@@ -65,32 +64,47 @@ pub enum FResult {
 }
 
 impl FResult {
-    pub fn from_fresult(fresult: esys::FRESULT) -> FResult {
+    /// This function converts a FRESULT into a Result.
+    /// FR_OK becomes Ok(()), and the other codes become the FResult variants.
+    pub fn from_fresult(fresult: esys::FRESULT) -> Result<(), FResult> {
         match fresult {
-            FRESULT::FR_OK => FResult::FOk,
-            FRESULT::FR_DISK_ERR => FResult::FDiskErr,
-            FRESULT::FR_INT_ERR => FResult::FIntErr,
-            FRESULT::FR_NOT_READY => FResult::FNotReady,
-            FRESULT::FR_NO_FILE => FResult::FNoFile,
-            FRESULT::FR_NO_PATH => FResult::FNoPath,
-            FRESULT::FR_INVALID_NAME => FResult::FInvalidName,
-            FRESULT::FR_DENIED => FResult::FDenied,
-            FRESULT::FR_EXIST => FResult::FExist,
-            FRESULT::FR_INVALID_OBJECT => FResult::FInvalidObject,
-            FRESULT::FR_WRITE_PROTECTED => FResult::FWriteProtected,
-            FRESULT::FR_INVALID_DRIVE => FResult::FInvalidDrive,
-            FRESULT::FR_NOT_ENABLED => FResult::FNotEnabled,
-            FRESULT::FR_NO_FILESYSTEM => FResult::FNoFilesystem,
-            FRESULT::FR_MKFS_ABORTED => FResult::FMfksAborted,
-            FRESULT::FR_TIMEOUT => FResult::FTimeOut,
-            FRESULT::FR_LOCKED => FResult::FLocked,
-            FRESULT::FR_NOT_ENOUGH_CORE => FResult::FNotEnoughCore,
-            FRESULT::FR_TOO_MANY_OPEN_FILES => FResult::FTooManyOpenFiles,
-            FRESULT::FR_INVALID_PARAMETER => FResult::FInvalidParameter,
+            FRESULT::FR_OK => Ok(()),
+            FRESULT::FR_DISK_ERR => Err(FResult::FDiskErr),
+            FRESULT::FR_INT_ERR => Err(FResult::FIntErr),
+            FRESULT::FR_NOT_READY => Err(FResult::FNotReady),
+            FRESULT::FR_NO_FILE => Err(FResult::FNoFile),
+            FRESULT::FR_NO_PATH => Err(FResult::FNoPath),
+            FRESULT::FR_INVALID_NAME => Err(FResult::FInvalidName),
+            FRESULT::FR_DENIED => Err(FResult::FDenied),
+            FRESULT::FR_EXIST => Err(FResult::FExist),
+            FRESULT::FR_INVALID_OBJECT => Err(FResult::FInvalidObject),
+            FRESULT::FR_WRITE_PROTECTED => Err(FResult::FWriteProtected),
+            FRESULT::FR_INVALID_DRIVE => Err(FResult::FInvalidDrive),
+            FRESULT::FR_NOT_ENABLED => Err(FResult::FNotEnabled),
+            FRESULT::FR_NO_FILESYSTEM => Err(FResult::FNoFilesystem),
+            FRESULT::FR_MKFS_ABORTED => Err(FResult::FMfksAborted),
+            FRESULT::FR_TIMEOUT => Err(FResult::FTimeOut),
+            FRESULT::FR_LOCKED => Err(FResult::FLocked),
+            FRESULT::FR_NOT_ENOUGH_CORE => Err(FResult::FNotEnoughCore),
+            FRESULT::FR_TOO_MANY_OPEN_FILES => Err(FResult::FTooManyOpenFiles),
+            FRESULT::FR_INVALID_PARAMETER => Err(FResult::FInvalidParameter),
         }
     }
+
+    /// This function takes the FRESULT code.
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+}
+
+impl core::fmt::Display for FResult {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?} ({})", self, self.code())
+    }
 }
 
+impl core::error::Error for FResult {}
+
 //-------------------------------------------------------------------------------------------------
 // FatFs struct
 //-------------------------------------------------------------------------------------------------
@@ -108,6 +122,7 @@ impl FResult {
 /// use embeddedsw_rs as xemb;
 /// use xemb::{
 ///     ff::{FileAccessMode::*, FileMountOption::*, *},
+///     peripherals::Peripherals,
 ///     print, println,
 /// };
 ///
@@ -120,15 +135,17 @@ impl FResult {
 /// #[start]
 /// fn main(_argc: isize, _argv: *const *const u8) -> isize {
 ///
-///     // Mount Logical Drive
+///     // Mount Logical Drive 0
 ///     let path = "0:/\0";
 ///     let opt = Immediately;
-///     let mut fatfs = MaybeUninit::<FatFs>::uninit();
-///     if let Err(e) = FatFs::mount(&mut fatfs, path, opt) {
-///         println!("[Error] {:?}", e);
-///         return 0;
-///     }
-///     let mut fatfs = unsafe { fatfs.assume_init() };
+///     let mut p = Peripherals::take().unwrap();
+///     let fatfs = match p.fatfs(0, opt) {
+///         Ok(fatfs) => fatfs,
+///         Err(e) => {
+///             println!("[Error] {:?}", e);
+///             return 0;
+///         }
+///     };
 ///
 ///     // Open the test.dat file
 ///     let fname = "some.file\0";
@@ -177,7 +194,6 @@ impl FatFs {
     /// # Errors
     /// If this function cannot mount a logical volume,
     /// it returns one of the following FResult variants.
-    /// - FOk
     /// - FInvalidDrive
     /// - FDiskErr
     /// - FNotEnabled
@@ -195,14 +211,11 @@ impl FatFs {
         opt: FileMountOption,
     ) -> Result<(), FResult> {
         unsafe {
-            match esys::f_mount(
+            FResult::from_fresult(esys::f_mount(
                 fatfs.as_mut_ptr() as *mut esys::FATFS,
                 path.as_ptr() as *const esys::TCHAR,
                 opt as u8,
-            ) {
-                FRESULT::FR_OK => Ok(()),
-                fresult => Err(FResult::from_fresult(fresult)),
-            }
+            ))
         }
     }
 
@@ -229,7 +242,6 @@ impl Fil {
     ///
     /// If this function cannot open a logical volume,
     /// it returns one of the following FResult variants.
-    /// - FOk
     /// - FIntErr
     /// - FNotReady
     /// - FNoPath
@@ -258,14 +270,11 @@ impl Fil {
         mode: FileAccessMode,
     ) -> Result<(), FResult> {
         unsafe {
-            match esys::f_open(
+            FResult::from_fresult(esys::f_open(
                 fil.as_mut_ptr() as *mut esys::FIL,
                 path.as_ptr() as *const esys::TCHAR,
                 mode as u8,
-            ) {
-                FRESULT::FR_OK => Ok(()),
-                fresult => Err(FResult::from_fresult(fresult)),
-            }
+            ))
         }
     }
 
@@ -273,7 +282,6 @@ impl Fil {
     ///
     /// If this function cannot read a logical volume,
     /// it returns one of the following FResult variants.
-    /// - FOk
     /// - FDiskErr
     /// - FIntErr
     /// - FDenied
@@ -290,15 +298,13 @@ impl Fil {
         let br: u32 = 0;
         let fil = &mut self.inner;
         unsafe {
-            match esys::f_read(
+            FResult::from_fresult(esys::f_read(
                 fil as *mut esys::FIL,
                 buff.as_mut_ptr() as *mut c_void,
                 n as u32,
                 addr_of!(br) as *mut u32,
-            ) {
-                FRESULT::FR_OK => Ok(br as usize),
-                fresult => Err(FResult::from_fresult(fresult)),
-            }
+            ))
+            .map(|_| br as usize)
         }
     }
 
@@ -306,7 +312,6 @@ impl Fil {
     ///
     /// If this function cannot write a logical volume,
     /// it returns one of the following FResult variants.
-    /// - FOk
     /// - FDiskErr
     /// - FIntErr
     /// - FDenied
@@ -319,15 +324,13 @@ impl Fil {
         let bw: u32 = 0;
         let fil = &mut self.inner;
         unsafe {
-            match esys::f_write(
+            FResult::from_fresult(esys::f_write(
                 fil as *mut esys::FIL,
                 buff.as_ptr() as *const _,
                 n as u32,
                 addr_of!(bw) as *mut u32,
-            ) {
-                FRESULT::FR_OK => Ok(bw as usize),
-                fresult => Err(FResult::from_fresult(fresult)),
-            }
+            ))
+            .map(|_| bw as usize)
         }
     }
 
@@ -335,7 +338,6 @@ impl Fil {
     ///
     /// If this function cannot close a logical volume,
     /// it returns one of the following FResult variants.
-    /// - FOk
     /// - FDiskErr
     /// - FIntErr
     /// - FInvalidObject
@@ -344,11 +346,6 @@ impl Fil {
     /// please see [Fatfs](http://elm-chan.org/fsw/ff/doc/rc.html#de) library to get more detail of this bindings.
     ///
     pub fn close(&mut self) -> Result<(), FResult> {
-        unsafe {
-            match esys::f_close(&mut self.inner) {
-                FRESULT::FR_OK => Ok(()),
-                fresult => Err(FResult::from_fresult(fresult)),
-            }
-        }
+        unsafe { FResult::from_fresult(esys::f_close(&mut self.inner)) }
     }
 }
@@ -0,0 +1,460 @@
+use core::fmt;
+
+//-------------------------------------------------------------------------------------------------
+// FrameError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the errors of framing.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The frame does not fit in the buffer.
+    Overflow,
+    /// The frame is not encoded correctly.
+    Invalid,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::Overflow => write!(f, "the frame is too long"),
+            FrameError::Invalid => write!(f, "the frame is invalid"),
+        }
+    }
+}
+
+impl core::error::Error for FrameError {}
+
+//-------------------------------------------------------------------------------------------------
+// SLIP
+//-------------------------------------------------------------------------------------------------
+
+const SLIP_END: u8 = 0xc0;
+const SLIP_ESC: u8 = 0xdb;
+const SLIP_ESC_END: u8 = 0xdc;
+const SLIP_ESC_ESC: u8 = 0xdd;
+
+/// A decoder of SLIP (RFC 1055) frames, which holds up to `N` bytes.
+///
+/// Bytes are pushed one by one, and a frame is returned at each `END`.
+/// Empty frames are ignored, so the sender can start frames with `END`.
+/// After an error, the bytes until the next `END` are discarded.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::framing::SlipDecoder;
+///
+/// let (_, mut rx) = UART1_RX.split().unwrap();
+/// let mut slip = SlipDecoder::<256>::new();
+/// while let Some(b) = rx.pop() {
+///     match slip.push(b) {
+///         Some(Ok(frame)) => handle(frame),
+///         Some(Err(e)) => println!("bad frame: {}", e),
+///         None => {}
+///     }
+/// }
+/// ```
+pub struct SlipDecoder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    escape: bool,
+    error: Option<FrameError>,
+    done: bool,
+}
+
+impl<const N: usize> SlipDecoder<N> {
+    /// This function creates a decoder.
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            escape: false,
+            error: None,
+            done: false,
+        }
+    }
+
+    /// This function decodes a byte,
+    /// and takes the frame if the byte ends it.
+    pub fn push(&mut self, b: u8) -> Option<Result<&[u8], FrameError>> {
+        if self.done {
+            self.reset();
+        }
+        let b = match (self.escape, b) {
+            (_, SLIP_END) => {
+                let escape = self.escape;
+                if self.len == 0 && self.error.is_none() && !escape {
+                    return None;
+                }
+                self.done = true;
+                return Some(match self.error {
+                    Some(e) => Err(e),
+                    None if escape => Err(FrameError::Invalid),
+                    None => Ok(&self.buf[..self.len]),
+                });
+            }
+            (false, SLIP_ESC) => {
+                self.escape = true;
+                return None;
+            }
+            (false, b) => b,
+            (true, SLIP_ESC_END) => SLIP_END,
+            (true, SLIP_ESC_ESC) => SLIP_ESC,
+            (true, _) => {
+                self.escape = false;
+                self.error.get_or_insert(FrameError::Invalid);
+                return None;
+            }
+        };
+        self.escape = false;
+        self.store(b);
+        None
+    }
+
+    /// This function discards the bytes of the current frame.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.escape = false;
+        self.error = None;
+        self.done = false;
+    }
+
+    fn store(&mut self, b: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        if self.len == N {
+            self.error = Some(FrameError::Overflow);
+            return;
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+    }
+}
+
+impl<const N: usize> Default for SlipDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// This function encodes the data into a SLIP frame,
+/// which starts and ends with `END`, and takes its length.
+///
+/// # Errors
+/// If the frame does not fit in the buffer, it returns `Overflow`.
+pub fn slip_encode(data: &[u8], out: &mut [u8]) -> Result<usize, FrameError> {
+    let mut writer = Writer { out, len: 0 };
+    writer.put(SLIP_END)?;
+    for &b in data {
+        match b {
+            SLIP_END => {
+                writer.put(SLIP_ESC)?;
+                writer.put(SLIP_ESC_END)?;
+            }
+            SLIP_ESC => {
+                writer.put(SLIP_ESC)?;
+                writer.put(SLIP_ESC_ESC)?;
+            }
+            b => writer.put(b)?,
+        }
+    }
+    writer.put(SLIP_END)?;
+    Ok(writer.len)
+}
+
+//-------------------------------------------------------------------------------------------------
+// COBS
+//-------------------------------------------------------------------------------------------------
+
+/// A decoder of COBS frames delimited by 0x00, which holds up to `N` bytes.
+///
+/// Bytes are pushed one by one, and a frame is returned at each 0x00.
+/// Empty delimiters are ignored, so the sender can start frames with 0x00.
+/// After an error, the bytes until the next 0x00 are discarded.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::framing::CobsDecoder;
+///
+/// let (_, mut rx) = UART1_RX.split().unwrap();
+/// let mut cobs = CobsDecoder::<256>::new();
+/// let mut buf = [0; 64];
+/// let n = rx.read(&mut buf);
+/// for b in &buf[..n] {
+///     if let Some(Ok(frame)) = cobs.push(*b) {
+///         handle(frame);
+///     }
+/// }
+/// ```
+pub struct CobsDecoder<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    /// The code byte of the current block, 0 before the first block.
+    code: u8,
+    /// The number of data bytes left in the current block.
+    remaining: u8,
+    error: Option<FrameError>,
+    done: bool,
+}
+
+impl<const N: usize> CobsDecoder<N> {
+    /// This function creates a decoder.
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; N],
+            len: 0,
+            code: 0,
+            remaining: 0,
+            error: None,
+            done: false,
+        }
+    }
+
+    /// This function decodes a byte,
+    /// and takes the frame if the byte ends it.
+    pub fn push(&mut self, b: u8) -> Option<Result<&[u8], FrameError>> {
+        if self.done {
+            self.reset();
+        }
+        if b == 0 {
+            if self.code == 0 && self.error.is_none() {
+                return None;
+            }
+            self.done = true;
+            return Some(match self.error {
+                Some(e) => Err(e),
+                None if self.remaining != 0 => Err(FrameError::Invalid),
+                None => Ok(&self.buf[..self.len]),
+            });
+        }
+        if self.remaining == 0 {
+            // a block of less than 254 bytes is followed by a zero,
+            // except the last block of the frame.
+            if self.code != 0 && self.code != 0xff {
+                self.store(0);
+            }
+            self.code = b;
+            self.remaining = b - 1;
+        } else {
+            self.store(b);
+            self.remaining -= 1;
+        }
+        None
+    }
+
+    /// This function discards the bytes of the current frame.
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.code = 0;
+        self.remaining = 0;
+        self.error = None;
+        self.done = false;
+    }
+
+    fn store(&mut self, b: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        if self.len == N {
+            self.error = Some(FrameError::Overflow);
+            return;
+        }
+        self.buf[self.len] = b;
+        self.len += 1;
+    }
+}
+
+impl<const N: usize> Default for CobsDecoder<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// This function encodes the data into a COBS frame,
+/// which ends with 0x00, and takes its length.
+///
+/// # Errors
+/// If the frame does not fit in the buffer, it returns `Overflow`.
+pub fn cobs_encode(data: &[u8], out: &mut [u8]) -> Result<usize, FrameError> {
+    let mut writer = Writer { out, len: 0 };
+    let mut code_pos = writer.len;
+    let mut code = 1;
+    writer.put(0)?;
+    for &b in data {
+        if b != 0 {
+            writer.put(b)?;
+            code += 1;
+        }
+        if b == 0 || code == 0xff {
+            writer.out[code_pos] = code;
+            code_pos = writer.len;
+            code = 1;
+            writer.put(0)?;
+        }
+    }
+    writer.out[code_pos] = code;
+    writer.put(0)?;
+    Ok(writer.len)
+}
+
+/// The output buffer of encoders.
+struct Writer<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl Writer<'_> {
+    fn put(&mut self, b: u8) -> Result<(), FrameError> {
+        let slot = self.out.get_mut(self.len).ok_or(FrameError::Overflow)?;
+        *slot = b;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The lengths around the COBS block size of 254 bytes.
+    const LENGTHS: [usize; 5] = [253, 254, 255, 508, 509];
+
+    /// Data without zeros, which makes the longest COBS blocks.
+    fn non_zero(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 255 + 1) as u8).collect()
+    }
+
+    /// Data with all byte values, including 0x00, `END` and `ESC`.
+    fn all_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7) as u8).collect()
+    }
+
+    fn slip_round_trip(data: &[u8]) {
+        let mut out = vec![0; 2 * data.len() + 2];
+        let n = slip_encode(data, &mut out).unwrap();
+        let mut slip = SlipDecoder::<1024>::new();
+        let (last, rest) = out[..n].split_last().unwrap();
+        for &b in rest {
+            assert_eq!(slip.push(b), None);
+        }
+        assert_eq!(slip.push(*last), Some(Ok(data)), "len {}", data.len());
+    }
+
+    fn cobs_round_trip(data: &[u8]) {
+        let mut out = vec![0; data.len() + data.len() / 254 + 2];
+        let n = cobs_encode(data, &mut out).unwrap();
+        assert!(!out[..n - 1].contains(&0));
+        let mut cobs = CobsDecoder::<1024>::new();
+        let (last, rest) = out[..n].split_last().unwrap();
+        for &b in rest {
+            assert_eq!(cobs.push(b), None);
+        }
+        assert_eq!(cobs.push(*last), Some(Ok(data)), "len {}", data.len());
+    }
+
+    #[test]
+    fn slip_round_trip_around_block_sizes() {
+        for len in LENGTHS {
+            slip_round_trip(&non_zero(len));
+            slip_round_trip(&all_bytes(len));
+        }
+    }
+
+    #[test]
+    fn cobs_round_trip_around_block_sizes() {
+        for len in LENGTHS {
+            cobs_round_trip(&non_zero(len));
+            cobs_round_trip(&all_bytes(len));
+        }
+    }
+
+    #[test]
+    fn empty_frames() {
+        let mut out = [0; 4];
+        assert_eq!(slip_encode(&[], &mut out), Ok(2));
+        let mut slip = SlipDecoder::<16>::new();
+        // empty SLIP frames are ignored.
+        assert_eq!(slip.push(out[0]), None);
+        assert_eq!(slip.push(out[1]), None);
+
+        assert_eq!(cobs_encode(&[], &mut out), Ok(2));
+        let mut cobs = CobsDecoder::<16>::new();
+        assert_eq!(cobs.push(out[0]), None);
+        assert_eq!(cobs.push(out[1]), Some(Ok(&[][..])));
+        // a delimiter without a block is ignored.
+        assert_eq!(cobs.push(0), None);
+    }
+
+    #[test]
+    fn bad_escape_discards_the_frame() {
+        let mut slip = SlipDecoder::<16>::new();
+        for b in [b'a', SLIP_ESC, b'b', b'c'] {
+            assert_eq!(slip.push(b), None);
+        }
+        assert_eq!(slip.push(SLIP_END), Some(Err(FrameError::Invalid)));
+        assert_eq!(slip.push(SLIP_ESC), None);
+        assert_eq!(slip.push(SLIP_END), Some(Err(FrameError::Invalid)));
+        // the next frame is decoded.
+        assert_eq!(slip.push(b'd'), None);
+        assert_eq!(slip.push(SLIP_END), Some(Ok(&b"d"[..])));
+
+        // the block says 4 data bytes, but the frame ends after 2.
+        let mut cobs = CobsDecoder::<16>::new();
+        for b in [5, 1, 2] {
+            assert_eq!(cobs.push(b), None);
+        }
+        assert_eq!(cobs.push(0), Some(Err(FrameError::Invalid)));
+        for b in [2, 9] {
+            assert_eq!(cobs.push(b), None);
+        }
+        assert_eq!(cobs.push(0), Some(Ok(&[9][..])));
+    }
+
+    #[test]
+    fn overflow() {
+        let mut out = [0; 8];
+        assert_eq!(slip_encode(&[1; 7], &mut out), Err(FrameError::Overflow));
+        assert_eq!(cobs_encode(&[1; 7], &mut out), Err(FrameError::Overflow));
+        assert_eq!(slip_encode(&[1; 6], &mut out), Ok(8));
+        assert_eq!(cobs_encode(&[1; 6], &mut out), Ok(8));
+
+        let mut slip = SlipDecoder::<4>::new();
+        for b in [1, 2, 3, 4, 5] {
+            assert_eq!(slip.push(b), None);
+        }
+        assert_eq!(slip.push(SLIP_END), Some(Err(FrameError::Overflow)));
+        for b in [1, 2, 3, 4] {
+            assert_eq!(slip.push(b), None);
+        }
+        assert_eq!(slip.push(SLIP_END), Some(Ok(&[1, 2, 3, 4][..])));
+
+        let mut cobs = CobsDecoder::<4>::new();
+        for b in [6, 1, 2, 3, 4, 5] {
+            assert_eq!(cobs.push(b), None);
+        }
+        assert_eq!(cobs.push(0), Some(Err(FrameError::Overflow)));
+        for b in [5, 1, 2, 3, 4] {
+            assert_eq!(cobs.push(b), None);
+        }
+        assert_eq!(cobs.push(0), Some(Ok(&[1, 2, 3, 4][..])));
+    }
+
+    #[test]
+    fn no_frame_without_delimiter() {
+        let mut out = [0; 16];
+        let n = slip_encode(b"abc", &mut out).unwrap();
+        let mut slip = SlipDecoder::<16>::new();
+        for &b in &out[..n - 1] {
+            assert_eq!(slip.push(b), None);
+        }
+        assert_eq!(slip.push(SLIP_END), Some(Ok(&b"abc"[..])));
+
+        let n = cobs_encode(b"abc", &mut out).unwrap();
+        let mut cobs = CobsDecoder::<16>::new();
+        for &b in &out[..n - 1] {
+            assert_eq!(cobs.push(b), None);
+        }
+        // the frame is not taken until the delimiter arrives.
+        assert_eq!(cobs.push(0), Some(Ok(&b"abc"[..])));
+    }
+}
@@ -0,0 +1,639 @@
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    mem::{size_of, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+#[cfg(test)]
+use crate::host::Mutex;
+#[cfg(not(test))]
+use crate::xscugic::Mutex;
+
+//-------------------------------------------------------------------------------------------------
+// Constants
+//-------------------------------------------------------------------------------------------------
+
+/// The alignment of blocks, which is the same as malloc of newlib.
+const ALIGN: usize = 2 * size_of::<usize>();
+const ALIGN_LOG2: u32 = ALIGN.trailing_zeros();
+
+/// The header of a block holds the previous physical block and the size.
+const HEADER_SIZE: usize = 2 * size_of::<usize>();
+
+/// A free block also holds the links of the free list in the payload.
+const MIN_BLOCK_SIZE: usize = HEADER_SIZE + 2 * size_of::<usize>();
+
+/// Each first-level class is split into 2^SL_LOG2 second-level classes.
+const SL_LOG2: u32 = 4;
+const SL_COUNT: usize = 1 << SL_LOG2;
+
+/// Blocks smaller than SMALL_BLOCK_SIZE are in the first-level class 0,
+/// which is split linearly.
+const FL_SHIFT: u32 = SL_LOG2 + ALIGN_LOG2;
+const SMALL_BLOCK_SIZE: usize = 1 << FL_SHIFT;
+const FL_COUNT: usize = (usize::BITS - FL_SHIFT + 1) as usize;
+
+const FREE_BIT: usize = 0b1;
+
+//-------------------------------------------------------------------------------------------------
+// Block
+//-------------------------------------------------------------------------------------------------
+
+/// The physical block.
+/// `next_free` and `prev_free` are valid only while the block is free,
+/// and they overlap the payload of an allocated block.
+/// The links are `None` at the ends, so no block is placed at address 0.
+#[repr(C)]
+struct Block {
+    prev_phys: Option<NonNull<Block>>,
+    size: usize,
+    next_free: Option<NonNull<Block>>,
+    prev_free: Option<NonNull<Block>>,
+}
+
+impl Block {
+    #[inline(always)]
+    unsafe fn size(this: *mut Block) -> usize {
+        (*this).size & !FREE_BIT
+    }
+
+    #[inline(always)]
+    unsafe fn set_size(this: *mut Block, size: usize) {
+        (*this).size = size | ((*this).size & FREE_BIT);
+    }
+
+    #[inline(always)]
+    unsafe fn is_free(this: *mut Block) -> bool {
+        (*this).size & FREE_BIT != 0
+    }
+
+    #[inline(always)]
+    unsafe fn set_free(this: *mut Block, free: bool) {
+        if free {
+            (*this).size |= FREE_BIT;
+        } else {
+            (*this).size &= !FREE_BIT;
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn next_phys(this: *mut Block) -> *mut Block {
+        (this as *mut u8).add(Block::size(this)) as *mut Block
+    }
+
+    #[inline(always)]
+    unsafe fn payload(this: *mut Block) -> *mut u8 {
+        (this as *mut u8).add(HEADER_SIZE)
+    }
+
+    #[inline(always)]
+    unsafe fn from_payload(ptr: *mut u8) -> *mut Block {
+        ptr.sub(HEADER_SIZE) as *mut Block
+    }
+
+    /// This function splits the block at the size,
+    /// and returns the rear block, which is free.
+    unsafe fn split(this: *mut Block, size: usize) -> *mut Block {
+        let rest = (this as *mut u8).add(size) as *mut Block;
+        (*rest).size = (Block::size(this) - size) | FREE_BIT;
+        (*rest).prev_phys = NonNull::new(this);
+        (*Block::next_phys(rest)).prev_phys = NonNull::new(rest);
+        Block::set_size(this, size);
+        rest
+    }
+
+    /// This function merges the next physical block into the block.
+    unsafe fn absorb(this: *mut Block, next: *mut Block) {
+        Block::set_size(this, Block::size(this) + Block::size(next));
+        (*Block::next_phys(this)).prev_phys = NonNull::new(this);
+    }
+}
+
+#[inline(always)]
+const fn align_up(value: usize, align: usize) -> usize {
+    (value + align - 1) & !(align - 1)
+}
+
+/// This function takes the first and second level classes of the size.
+#[inline(always)]
+fn mapping_insert(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK_SIZE {
+        (0, size / (SMALL_BLOCK_SIZE / SL_COUNT))
+    } else {
+        let msb = usize::BITS - 1 - size.leading_zeros();
+        let fl = (msb - FL_SHIFT + 1) as usize;
+        let sl = (size >> (msb - SL_LOG2)) & (SL_COUNT - 1);
+        (fl, sl)
+    }
+}
+
+/// This function takes the classes whose blocks are all larger than the size.
+#[inline(always)]
+fn mapping_search(size: usize) -> Option<(usize, usize)> {
+    let size = if size < SMALL_BLOCK_SIZE {
+        size
+    } else {
+        let msb = usize::BITS - 1 - size.leading_zeros();
+        size.checked_add((1 << (msb - SL_LOG2)) - 1)?
+    };
+    Some(mapping_insert(size))
+}
+
+//-------------------------------------------------------------------------------------------------
+// HeapStats
+//-------------------------------------------------------------------------------------------------
+
+/// Statistics of a heap.
+/// All sizes are in bytes and include the block headers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct HeapStats {
+    /// The size of the managed regions.
+    pub size: usize,
+    /// The size of allocated blocks.
+    pub used: usize,
+    /// The maximum of `used` since the heap was initialized.
+    pub peak: usize,
+    /// The size of free blocks.
+    pub free: usize,
+    /// The size of the largest free block.
+    pub largest_free: usize,
+}
+
+impl HeapStats {
+    /// This function takes the fragmentation in percent.
+    /// 0 means that all free memory is one block.
+    pub fn fragmentation(&self) -> u32 {
+        if self.free == 0 {
+            0
+        } else {
+            100 - (self.largest_free as u64 * 100 / self.free as u64) as u32
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Heap
+//-------------------------------------------------------------------------------------------------
+
+/// A TLSF (Two-Level Segregated Fit) heap.
+///
+/// Allocation and deallocation take constant time regardless of
+/// the number of blocks, and they never extend the heap.
+/// If no free block is large enough, the allocation fails at once.
+///
+/// The heap manages the regions passed to `init`.
+/// Do not give it the region which newlib malloc (sbrk) uses,
+/// that is, do not use `init_from_linker` together with `XAllocator`.
+pub struct Heap {
+    fl_bitmap: usize,
+    sl_bitmap: [usize; FL_COUNT],
+    free_lists: [[Option<NonNull<Block>>; SL_COUNT]; FL_COUNT],
+    size: usize,
+    used: usize,
+    peak: usize,
+}
+
+unsafe impl Send for Heap {}
+
+impl Heap {
+    /// This function creates a heap which has no region.
+    pub const fn empty() -> Self {
+        Self {
+            fl_bitmap: 0,
+            sl_bitmap: [0; FL_COUNT],
+            free_lists: [[None; SL_COUNT]; FL_COUNT],
+            size: 0,
+            used: 0,
+            peak: 0,
+        }
+    }
+
+    /// This function adds the memory region to the heap.
+    ///
+    /// # Safety
+    /// The region must be valid for reads and writes,
+    /// and must not be used by anything else while the heap is alive.
+    ///
+    /// A region at address 0, such as ATCM of the R5, loses its first
+    /// `ALIGN` bytes, because no block can be placed at address 0.
+    ///
+    /// # Panics
+    /// If the region is too small to hold a block, it panics.
+    pub unsafe fn init(&mut self, start: usize, size: usize) {
+        let end = (start + size) & !(ALIGN - 1);
+        let start = align_up(start.max(ALIGN), ALIGN);
+        assert!(
+            end > start && end - start >= MIN_BLOCK_SIZE + HEADER_SIZE,
+            "the heap region is too small"
+        );
+
+        // the sentinel block at the end is never free,
+        // so the last block never merges beyond the region.
+        let block_size = end - start - HEADER_SIZE;
+        let block = start as *mut Block;
+        let sentinel = (end - HEADER_SIZE) as *mut Block;
+        (*block).prev_phys = None;
+        (*block).size = block_size | FREE_BIT;
+        (*sentinel).prev_phys = NonNull::new(block);
+        (*sentinel).size = 0;
+
+        self.insert(block);
+        self.size += block_size;
+    }
+
+    /// This function adds the static buffer to the heap.
+    ///
+    /// # Example
+    /// ```
+    /// use core::mem::MaybeUninit;
+    /// use embeddedsw_rs::heap::Heap;
+    ///
+    /// static mut BUFFER: [MaybeUninit<u8>; 0x4000] = [MaybeUninit::uninit(); 0x4000];
+    ///
+    /// let mut heap = Heap::empty();
+    /// heap.init_from_slice(unsafe { &mut *core::ptr::addr_of_mut!(BUFFER) });
+    /// ```
+    pub fn init_from_slice(&mut self, mem: &'static mut [MaybeUninit<u8>]) {
+        unsafe { self.init(mem.as_mut_ptr() as usize, mem.len()) }
+    }
+
+    /// This function adds the heap region of the linker script
+    /// (from `_heap_start` to `_heap_end`) to the heap.
+    ///
+    /// # Safety
+    /// newlib malloc must not be used,
+    /// because it allocates from the same region.
+    pub unsafe fn init_from_linker(&mut self) {
+        extern "C" {
+            static mut _heap_start: u8;
+            static mut _heap_end: u8;
+        }
+        let start = ptr::addr_of_mut!(_heap_start) as usize;
+        let end = ptr::addr_of_mut!(_heap_end) as usize;
+        self.init(start, end - start);
+    }
+
+    /// This function allocates a block which fits the layout.
+    /// If there is no large enough free block, it returns None.
+    pub fn allocate(&mut self, layout: Layout) -> Option<NonNull<u8>> {
+        let payload = layout.size().checked_add(ALIGN - 1)? & !(ALIGN - 1);
+        let block_size = payload.checked_add(HEADER_SIZE)?.max(MIN_BLOCK_SIZE);
+        let align = layout.align();
+        // an aligned block needs room for a free block in front of it.
+        let search_size = if align <= ALIGN {
+            block_size
+        } else {
+            block_size.checked_add(align + MIN_BLOCK_SIZE)?
+        };
+
+        unsafe {
+            let (fl, sl) = self.find_suitable(search_size)?;
+            let mut block = self.free_lists[fl][sl]?.as_ptr();
+            self.remove(block);
+
+            if align > ALIGN {
+                let addr = Block::payload(block) as usize;
+                let mut aligned = align_up(addr, align);
+                if aligned != addr && aligned - addr < MIN_BLOCK_SIZE {
+                    aligned = align_up(addr + MIN_BLOCK_SIZE, align);
+                }
+                if aligned != addr {
+                    let rest = Block::split(block, aligned - addr);
+                    self.insert(block);
+                    block = rest;
+                }
+            }
+
+            if Block::size(block) - block_size >= MIN_BLOCK_SIZE {
+                let rest = Block::split(block, block_size);
+                self.insert(rest);
+            }
+            Block::set_free(block, false);
+
+            self.used += Block::size(block);
+            self.peak = self.peak.max(self.used);
+            NonNull::new(Block::payload(block))
+        }
+    }
+
+    /// This function deallocates the block.
+    ///
+    /// # Safety
+    /// The pointer must be returned by `allocate` of this heap,
+    /// and must not be deallocated yet.
+    pub unsafe fn deallocate(&mut self, ptr: NonNull<u8>) {
+        let mut block = Block::from_payload(ptr.as_ptr());
+        self.used -= Block::size(block);
+        Block::set_free(block, true);
+
+        let next = Block::next_phys(block);
+        if Block::is_free(next) {
+            self.remove(next);
+            Block::absorb(block, next);
+        }
+        if let Some(prev) = (*block).prev_phys {
+            let prev = prev.as_ptr();
+            if Block::is_free(prev) {
+                self.remove(prev);
+                Block::absorb(prev, block);
+                block = prev;
+            }
+        }
+
+        self.insert(block);
+    }
+
+    /// This function takes the statistics of the heap.
+    ///
+    /// It searches the free list of the largest class,
+    /// so it is not bounded in time as `allocate` is.
+    pub fn stats(&self) -> HeapStats {
+        let mut largest_free = 0;
+        if self.fl_bitmap != 0 {
+            let fl =
+                (usize::BITS - 1 - self.fl_bitmap.leading_zeros()) as usize;
+            let sl_map = self.sl_bitmap[fl];
+            let sl = (usize::BITS - 1 - sl_map.leading_zeros()) as usize;
+            let mut block = self.free_lists[fl][sl];
+            while let Some(b) = block {
+                unsafe {
+                    largest_free = largest_free.max(Block::size(b.as_ptr()));
+                    block = (*b.as_ptr()).next_free;
+                }
+            }
+        }
+
+        HeapStats {
+            size: self.size,
+            used: self.used,
+            peak: self.peak,
+            free: self.size - self.used,
+            largest_free,
+        }
+    }
+
+    /// This function finds a non-empty class
+    /// whose blocks are all larger than the size.
+    fn find_suitable(&self, size: usize) -> Option<(usize, usize)> {
+        let (fl, sl) = mapping_search(size)?;
+        if fl >= FL_COUNT {
+            return None;
+        }
+
+        let mut sl_map = self.sl_bitmap[fl] & (usize::MAX << sl);
+        let fl = if sl_map != 0 {
+            fl
+        } else {
+            let fl_map =
+                self.fl_bitmap & usize::MAX.checked_shl(fl as u32 + 1)?;
+            if fl_map == 0 {
+                return None;
+            }
+            let fl = fl_map.trailing_zeros() as usize;
+            sl_map = self.sl_bitmap[fl];
+            fl
+        };
+        Some((fl, sl_map.trailing_zeros() as usize))
+    }
+
+    unsafe fn insert(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping_insert(Block::size(block));
+        let head = self.free_lists[fl][sl];
+        (*block).next_free = head;
+        (*block).prev_free = None;
+        if let Some(head) = head {
+            (*head.as_ptr()).prev_free = NonNull::new(block);
+        }
+        self.free_lists[fl][sl] = NonNull::new(block);
+        self.fl_bitmap |= 1 << fl;
+        self.sl_bitmap[fl] |= 1 << sl;
+    }
+
+    unsafe fn remove(&mut self, block: *mut Block) {
+        let (fl, sl) = mapping_insert(Block::size(block));
+        let next = (*block).next_free;
+        let prev = (*block).prev_free;
+        if let Some(next) = next {
+            (*next.as_ptr()).prev_free = prev;
+        }
+        if let Some(prev) = prev {
+            (*prev.as_ptr()).next_free = next;
+        }
+        if self.free_lists[fl][sl] == NonNull::new(block) {
+            self.free_lists[fl][sl] = next;
+            if next.is_none() {
+                self.sl_bitmap[fl] &= !(1 << sl);
+                if self.sl_bitmap[fl] == 0 {
+                    self.fl_bitmap &= !(1 << fl);
+                }
+            }
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// LockedHeap
+//-------------------------------------------------------------------------------------------------
+
+/// A TLSF heap which can be used as the global allocator.
+/// The heap is locked by masking IRQ and FIQ,
+/// so it can be used from interrupt handlers.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::heap::LockedHeap;
+///
+/// #[global_allocator]
+/// static HEAP: LockedHeap = LockedHeap::empty();
+///
+/// // in main, before any allocation
+/// unsafe { HEAP.init_from_linker() };
+///
+/// let stats = HEAP.stats();
+/// println!("used {} / {} bytes, {}% fragmented",
+///     stats.used, stats.size, stats.fragmentation());
+/// ```
+pub struct LockedHeap {
+    heap: Mutex<Heap>,
+}
+
+impl LockedHeap {
+    /// This function creates a heap which has no region.
+    pub const fn empty() -> Self {
+        Self {
+            heap: Mutex::new(Heap::empty()),
+        }
+    }
+
+    /// This function adds the memory region to the heap.
+    ///
+    /// # Safety
+    /// See `Heap::init`.
+    pub unsafe fn init(&self, start: usize, size: usize) {
+        self.heap.lock(|heap| heap.init(start, size))
+    }
+
+    /// This function adds the static buffer to the heap.
+    pub fn init_from_slice(&self, mem: &'static mut [MaybeUninit<u8>]) {
+        self.heap.lock(|heap| heap.init_from_slice(mem))
+    }
+
+    /// This function adds the heap region of the linker script to the heap.
+    ///
+    /// # Safety
+    /// See `Heap::init_from_linker`.
+    pub unsafe fn init_from_linker(&self) {
+        self.heap.lock(|heap| heap.init_from_linker())
+    }
+
+    /// This function takes the statistics of the heap.
+    pub fn stats(&self) -> HeapStats {
+        self.heap.lock(|heap| heap.stats())
+    }
+}
+
+unsafe impl GlobalAlloc for LockedHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.heap.lock(|heap| {
+            heap.allocate(layout)
+                .map_or(ptr::null_mut(), |ptr| ptr.as_ptr())
+        })
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            self.heap.lock(|heap| heap.deallocate(ptr))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The smallest block size of the class.
+    fn class_min(fl: usize, sl: usize) -> usize {
+        if fl == 0 {
+            sl * (SMALL_BLOCK_SIZE / SL_COUNT)
+        } else {
+            let base = SMALL_BLOCK_SIZE << (fl - 1);
+            base + sl * (base / SL_COUNT)
+        }
+    }
+
+    fn heap(size: usize) -> Heap {
+        let mem = Vec::leak(vec![0u64; size / 8]);
+        let mut heap = Heap::empty();
+        unsafe { heap.init(mem.as_mut_ptr() as usize, size) };
+        heap
+    }
+
+    fn layout(size: usize, align: usize) -> Layout {
+        Layout::from_size_align(size, align).unwrap()
+    }
+
+    #[test]
+    fn mapping_classes_bound_sizes() {
+        for size in (MIN_BLOCK_SIZE..1 << 20).step_by(ALIGN) {
+            let (fl, sl) = mapping_insert(size);
+            assert!(class_min(fl, sl) <= size);
+            let (fl, sl) = mapping_search(size).unwrap();
+            assert!(class_min(fl, sl) >= size, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn split_and_merge_restore_one_block() {
+        let mut heap = heap(0x1000);
+        let full = heap.stats();
+        assert_eq!(full.largest_free, full.size);
+
+        let a = heap.allocate(layout(100, 8)).unwrap();
+        let b = heap.allocate(layout(200, 8)).unwrap();
+        let c = heap.allocate(layout(300, 8)).unwrap();
+        assert!(heap.stats().used >= 600);
+        unsafe {
+            heap.deallocate(b);
+            heap.deallocate(a);
+            heap.deallocate(c);
+        }
+
+        let stats = heap.stats();
+        assert_eq!(stats.used, 0);
+        assert_eq!(stats.largest_free, full.size);
+        assert_eq!(stats.fragmentation(), 0);
+    }
+
+    #[test]
+    fn aligned_allocations() {
+        let mut heap = heap(0x4000);
+        let size = heap.stats().size;
+        let ptrs: Vec<_> = [32, 64, 256, 1024]
+            .iter()
+            .map(|&align| {
+                let ptr = heap.allocate(layout(40, align)).unwrap();
+                assert_eq!(ptr.as_ptr() as usize % align, 0);
+                ptr
+            })
+            .collect();
+        for ptr in ptrs {
+            unsafe { heap.deallocate(ptr) };
+        }
+        assert_eq!(heap.stats().largest_free, size);
+    }
+
+    #[test]
+    fn exhausted_heap_fails() {
+        let mut heap = heap(0x400);
+        assert!(heap.allocate(layout(0x400, 8)).is_none());
+        let ptr = heap.allocate(layout(0x100, 8)).unwrap();
+        unsafe { heap.deallocate(ptr) };
+        assert!(heap.allocate(layout(isize::MAX as usize - 8, 8)).is_none());
+    }
+
+    #[test]
+    fn random_allocations_do_not_overlap() {
+        let mut heap = heap(0x10000);
+        let size = heap.stats().size;
+        let mut live: Vec<(NonNull<u8>, usize, u8)> = Vec::new();
+        let mut seed = 1u32;
+        for i in 0..4000 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let r = (seed >> 8) as usize;
+            if !r.is_multiple_of(3) || live.is_empty() {
+                let len = 1 + r % 700;
+                let align = 8 << (r % 4);
+                if let Some(ptr) = heap.allocate(layout(len, align)) {
+                    assert_eq!(ptr.as_ptr() as usize % align, 0);
+                    unsafe { ptr.as_ptr().write_bytes(i as u8, len) };
+                    live.push((ptr, len, i as u8));
+                }
+            } else {
+                let (ptr, len, tag) = live.swap_remove(r % live.len());
+                let data =
+                    unsafe { core::slice::from_raw_parts(ptr.as_ptr(), len) };
+                assert!(data.iter().all(|b| *b == tag));
+                unsafe { heap.deallocate(ptr) };
+            }
+        }
+        for (ptr, _, _) in live {
+            unsafe { heap.deallocate(ptr) };
+        }
+        assert_eq!(heap.stats().largest_free, size);
+    }
+
+    #[test]
+    fn locked_heap_allocates() {
+        static HEAP: LockedHeap = LockedHeap::empty();
+        let mem = Vec::leak(vec![MaybeUninit::new(0u8); 0x1000]);
+        HEAP.init_from_slice(mem);
+        let layout = layout(64, 16);
+        unsafe {
+            let ptr = HEAP.alloc(layout);
+            assert!(!ptr.is_null());
+            assert_eq!(ptr as usize % 16, 0);
+            HEAP.dealloc(ptr, layout);
+        }
+        assert_eq!(HEAP.stats().used, 0);
+    }
+}
@@ -0,0 +1,34 @@
+// The critical section on the host, where tests call interrupt handlers
+// such as `executor::tick` directly to simulate interrupts.
+use core::cell::UnsafeCell;
+
+pub fn interrupt_free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    f()
+}
+
+pub fn wait_for_interrupt() {}
+
+pub struct Mutex<T> {
+    inner: std::sync::Mutex<UnsafeCell<T>>,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: std::sync::Mutex::new(UnsafeCell::new(value)),
+        }
+    }
+
+    pub fn lock<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let guard = self.inner.try_lock().expect("Mutex is locked recursively");
+        f(unsafe { &mut *guard.get() })
+    }
+}
@@ -1,14 +1,62 @@
-#![no_std]
-#![feature(alloc_error_handler)]
+#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(test), feature(allocator_api))]
+#![cfg_attr(
+    all(feature = "xalloc-global", not(test)),
+    feature(alloc_error_handler)
+)]
 
 pub extern crate embeddedsw_sys;
 pub use embeddedsw_sys as raw;
 
-pub mod xalloc;
+#[cfg(not(test))]
+pub mod cache;
+#[cfg(not(test))]
+pub mod console;
+pub mod executor;
+pub mod framing;
+pub mod heap;
+#[cfg(test)]
+mod host;
+#[cfg(not(test))]
+pub mod mpu;
+#[cfg(not(test))]
+pub mod peripherals;
+#[cfg(not(test))]
+pub mod region;
+#[cfg(not(test))]
+pub mod resource;
+#[cfg(not(test))]
+pub mod shell;
+#[cfg(not(test))]
+pub mod stack;
+#[cfg(not(test))]
+pub mod xil_assert;
+#[cfg(not(test))]
 pub mod xil_printf;
+#[cfg(not(test))]
 pub mod xscugic;
+#[cfg(not(test))]
+pub mod xstatus;
+#[cfg(not(test))]
+pub mod xtime;
 
-#[cfg(feature = "xilffs")]
+#[cfg(all(feature = "crash-record", not(test)))]
+pub mod crash;
+#[cfg(all(feature = "defmt", not(test)))]
+pub mod defmt_logger;
+#[cfg(all(feature = "xilffs", not(test)))]
 pub mod ff;
-#[cfg(feature = "xaxidma")]
+#[cfg(all(feature = "xalloc", not(test)))]
+pub mod xalloc;
+#[cfg(all(feature = "log", not(test)))]
+pub mod logger;
+#[cfg(all(feature = "panic-handler", not(test)))]
+pub mod panic;
+#[cfg(all(feature = "semihosting", not(test)))]
+pub mod semihosting;
+#[cfg(all(feature = "xaxidma", not(test)))]
 pub mod xaxidma;
+#[cfg(all(feature = "xipipsu", not(test)))]
+pub mod xipipsu;
+#[cfg(all(feature = "xuartps", not(test)))]
+pub mod xuartps;
@@ -0,0 +1,239 @@
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::{println, xscugic::Mutex, xtime::Instant};
+
+/// The maximum number of module filters.
+pub const MAX_FILTERS: usize = 8;
+
+static LOGGER: XilLogger = XilLogger;
+
+static DEFAULT_LEVEL: AtomicUsize =
+    AtomicUsize::new(LevelFilter::Info as usize);
+static TIMESTAMPS: AtomicBool = AtomicBool::new(true);
+static COLORS: AtomicBool = AtomicBool::new(false);
+static FILTERS: Mutex<[Option<(&str, LevelFilter)>; MAX_FILTERS]> =
+    Mutex::new([None; MAX_FILTERS]);
+
+//-------------------------------------------------------------------------------------------------
+// Configuration
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the errors of module filters.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    /// `MAX_FILTERS` filters are already set.
+    Full,
+}
+
+impl core::fmt::Display for FilterError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FilterError::Full => {
+                write!(f, "at most {} module filters can be set", MAX_FILTERS)
+            }
+        }
+    }
+}
+
+impl core::error::Error for FilterError {}
+
+/// This function installs the logger of the `log` crate,
+/// which writes records to the UART, with the default level.
+///
+/// Records are filtered at compile time by the `max_level_*`
+/// and `release_max_level_*` features of the `log` crate,
+/// and at run time by the default level and the levels of module paths.
+///
+/// With the `crash-record` feature, records are also appended to
+/// the log lines of the crash record (see `crash::log`).
+///
+/// The timestamps are taken by `xtime::Instant::now`. If the counter is
+/// 32 bits, log at least once in each period of the counter, or call
+/// `Instant::now` periodically, so the timestamps do not go back
+/// (see `Instant::now`).
+///
+/// # Errors
+/// If another logger is installed, it returns the error of `log`.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::logger;
+/// use log::{info, LevelFilter};
+///
+/// logger::init(LevelFilter::Debug).unwrap();
+/// logger::set_module_level("my_app::motor", LevelFilter::Trace).unwrap();
+/// logger::set_colors(true);
+/// info!("started");
+/// ```
+pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    set_level(level);
+    Ok(())
+}
+
+/// This function sets the level of the modules without their own filter.
+pub fn set_level(level: LevelFilter) {
+    DEFAULT_LEVEL.store(level as usize, Ordering::Relaxed);
+    update_max_level();
+}
+
+/// This function sets the level of the module path and its submodules.
+/// The filter with the longest matching path takes priority.
+///
+/// # Errors
+/// If `MAX_FILTERS` other paths are already set, it returns `FilterError::Full`.
+pub fn set_module_level(
+    path: &'static str,
+    level: LevelFilter,
+) -> Result<(), FilterError> {
+    FILTERS.lock(|filters| {
+        if let Some(filter) =
+            filters.iter_mut().flatten().find(|(p, _)| *p == path)
+        {
+            filter.1 = level;
+            return Ok(());
+        }
+        match filters.iter_mut().find(|f| f.is_none()) {
+            Some(slot) => {
+                *slot = Some((path, level));
+                Ok(())
+            }
+            None => Err(FilterError::Full),
+        }
+    })?;
+    update_max_level();
+    Ok(())
+}
+
+/// This function removes the filter of the module path.
+pub fn remove_module_level(path: &str) {
+    FILTERS.lock(|filters| {
+        for filter in filters.iter_mut() {
+            if matches!(filter, Some((p, _)) if *p == path) {
+                *filter = None;
+            }
+        }
+    });
+    update_max_level();
+}
+
+/// This function selects whether records have the time since boot.
+/// It is enabled by default.
+///
+/// The time goes back if no time is taken during a period of the counter,
+/// see `init`.
+pub fn set_timestamps(enabled: bool) {
+    TIMESTAMPS.store(enabled, Ordering::Relaxed);
+}
+
+/// This function selects whether levels are colored with ANSI escapes.
+/// It is disabled by default.
+pub fn set_colors(enabled: bool) {
+    COLORS.store(enabled, Ordering::Relaxed);
+}
+
+/// The macros of `log` skip records above `log::max_level`,
+/// so it is the highest level of all filters.
+fn update_max_level() {
+    let max = FILTERS.lock(|filters| {
+        filters
+            .iter()
+            .flatten()
+            .map(|(_, level)| *level)
+            .fold(default_level(), Ord::max)
+    });
+    log::set_max_level(max);
+}
+
+fn default_level() -> LevelFilter {
+    match DEFAULT_LEVEL.load(Ordering::Relaxed) {
+        0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    }
+}
+
+/// This function takes the level of the target,
+/// which is the module path unless it is given to the macro.
+fn level_of(target: &str) -> LevelFilter {
+    FILTERS.lock(|filters| {
+        filters
+            .iter()
+            .flatten()
+            .filter(|(path, _)| {
+                target.strip_prefix(path).is_some_and(|rest| {
+                    rest.is_empty() || rest.starts_with("::")
+                })
+            })
+            .max_by_key(|(path, _)| path.len())
+            .map_or_else(default_level, |(_, level)| *level)
+    })
+}
+
+//-------------------------------------------------------------------------------------------------
+// XilLogger
+//-------------------------------------------------------------------------------------------------
+
+struct XilLogger;
+
+impl Log for XilLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= level_of(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let (color, reset) = if COLORS.load(Ordering::Relaxed) {
+            (color(record.level()), "\x1b[0m")
+        } else {
+            ("", "")
+        };
+        if TIMESTAMPS.load(Ordering::Relaxed) {
+            let time = Instant::now().since_boot();
+            println!(
+                "[{:5}.{:06}] {}{:5}{} {}: {}",
+                time.as_secs(),
+                time.subsec_micros(),
+                color,
+                record.level(),
+                reset,
+                record.target(),
+                record.args()
+            );
+        } else {
+            println!(
+                "{}{:5}{} {}: {}",
+                color,
+                record.level(),
+                reset,
+                record.target(),
+                record.args()
+            );
+        }
+        #[cfg(feature = "crash-record")]
+        crate::crash::log(format_args!(
+            "{:5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        ));
+    }
+
+    fn flush(&self) {}
+}
+
+fn color(level: Level) -> &'static str {
+    match level {
+        Level::Error => "\x1b[31m",
+        Level::Warn => "\x1b[33m",
+        Level::Info => "\x1b[32m",
+        Level::Debug => "\x1b[36m",
+        Level::Trace => "\x1b[90m",
+    }
+}
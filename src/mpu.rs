@@ -0,0 +1,455 @@
+extern crate embeddedsw_sys;
+use core::arch::asm;
+use embeddedsw_sys as esys;
+
+use crate::{println, xscugic::interrupt_free};
+
+//-------------------------------------------------------------------------------------------------
+// MpuError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for errors of MPU configurations.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MpuError {
+    /// The size is not a power of two from 32 bytes to 4 GiB.
+    InvalidSize,
+    /// The base address is not aligned to the size.
+    Misaligned,
+    /// The region number is out of the regions of the MPU.
+    InvalidRegion,
+    /// No region is free.
+    NoFreeRegion,
+}
+
+//-------------------------------------------------------------------------------------------------
+// Attributes
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for cache policies of normal memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Cacheability {
+    NonCacheable,
+    WriteThrough,
+    WriteBack,
+    WriteBackWriteAllocate,
+}
+
+/// Enumerates for memory types.
+///
+/// Strongly-ordered memory is always shareable.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MemoryType {
+    StronglyOrdered,
+    Device {
+        shareable: bool,
+    },
+    Normal {
+        cache: Cacheability,
+        shareable: bool,
+    },
+}
+
+/// Enumerates for access permissions (AP[2:0]).
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AccessPermission {
+    /// No access
+    NoAccess = 0b000,
+    /// Privileged read/write, user no access
+    PrivRw = 0b001,
+    /// Privileged read/write, user read-only
+    PrivRwUserRo = 0b010,
+    /// Read/write
+    FullAccess = 0b011,
+    /// Privileged read-only, user no access
+    PrivRo = 0b101,
+    /// Read-only
+    ReadOnly = 0b110,
+}
+
+const DRACR_XN: u32 = 1 << 12;
+const DRACR_AP_SHIFT: u32 = 8;
+const DRACR_TEX_SHIFT: u32 = 3;
+const DRACR_S: u32 = 1 << 2;
+const DRACR_C: u32 = 1 << 1;
+const DRACR_B: u32 = 1 << 0;
+
+const DRSR_EN: u32 = 1;
+const DRSR_SIZE_SHIFT: u32 = 1;
+const DRSR_SIZE_MASK: u32 = 0x1f;
+
+/// Attributes of a MPU region,
+/// which are encoded into the region access control register (DRACR).
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::mpu::{AccessPermission, Cacheability, MemoryType, RegionAttributes};
+///
+/// // DMA buffers which are not cached
+/// let dma = RegionAttributes {
+///     memory: MemoryType::Normal { cache: Cacheability::NonCacheable, shareable: true },
+///     access: AccessPermission::FullAccess,
+///     execute_never: true,
+/// };
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegionAttributes {
+    pub memory: MemoryType,
+    pub access: AccessPermission,
+    pub execute_never: bool,
+}
+
+impl RegionAttributes {
+    /// Attributes which fault on any access, such as a guard region.
+    pub const NO_ACCESS: RegionAttributes = RegionAttributes {
+        memory: MemoryType::StronglyOrdered,
+        access: AccessPermission::NoAccess,
+        execute_never: true,
+    };
+
+    /// This function encodes the attributes into DRACR bits.
+    pub const fn bits(&self) -> u32 {
+        let (tex, c, b, s) = match self.memory {
+            MemoryType::StronglyOrdered => (0b000, false, false, false),
+            MemoryType::Device { shareable: true } => {
+                (0b000, false, true, false)
+            }
+            MemoryType::Device { shareable: false } => {
+                (0b010, false, false, false)
+            }
+            MemoryType::Normal { cache, shareable } => match cache {
+                Cacheability::NonCacheable => (0b001, false, false, shareable),
+                Cacheability::WriteThrough => (0b000, true, false, shareable),
+                Cacheability::WriteBack => (0b000, true, true, shareable),
+                Cacheability::WriteBackWriteAllocate => {
+                    (0b001, true, true, shareable)
+                }
+            },
+        };
+
+        let mut bits =
+            (self.access as u32) << DRACR_AP_SHIFT | tex << DRACR_TEX_SHIFT;
+        if self.execute_never {
+            bits |= DRACR_XN;
+        }
+        if s {
+            bits |= DRACR_S;
+        }
+        if c {
+            bits |= DRACR_C;
+        }
+        if b {
+            bits |= DRACR_B;
+        }
+        bits
+    }
+
+    /// This function decodes DRACR bits.
+    /// If the bits are not one of the encodings of `bits`, it returns None.
+    pub const fn from_bits(bits: u32) -> Option<Self> {
+        let access = match (bits >> DRACR_AP_SHIFT) & 0b111 {
+            0b000 => AccessPermission::NoAccess,
+            0b001 => AccessPermission::PrivRw,
+            0b010 => AccessPermission::PrivRwUserRo,
+            0b011 => AccessPermission::FullAccess,
+            0b101 => AccessPermission::PrivRo,
+            0b110 => AccessPermission::ReadOnly,
+            _ => return None,
+        };
+        let shareable = bits & DRACR_S != 0;
+        let tex = (bits >> DRACR_TEX_SHIFT) & 0b111;
+        let memory = match (tex, bits & (DRACR_C | DRACR_B)) {
+            (0b000, 0) => MemoryType::StronglyOrdered,
+            (0b000, DRACR_B) => MemoryType::Device { shareable: true },
+            (0b010, 0) => MemoryType::Device { shareable: false },
+            (0b001, 0) => MemoryType::Normal {
+                cache: Cacheability::NonCacheable,
+                shareable,
+            },
+            (0b000, DRACR_C) => MemoryType::Normal {
+                cache: Cacheability::WriteThrough,
+                shareable,
+            },
+            (0b000, 0b11) => MemoryType::Normal {
+                cache: Cacheability::WriteBack,
+                shareable,
+            },
+            (0b001, 0b11) => MemoryType::Normal {
+                cache: Cacheability::WriteBackWriteAllocate,
+                shareable,
+            },
+            _ => return None,
+        };
+
+        Some(Self {
+            memory,
+            access,
+            execute_never: bits & DRACR_XN != 0,
+        })
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// RegionSize
+//-------------------------------------------------------------------------------------------------
+
+/// The size of a MPU region, which is a power of two from 32 bytes to 4 GiB.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RegionSize(u32);
+
+impl RegionSize {
+    /// This function creates the size from bytes.
+    ///
+    /// # Errors
+    /// If the size is not a power of two from 32 bytes to 4 GiB,
+    /// it returns `InvalidSize`.
+    pub const fn from_bytes(bytes: u64) -> Result<Self, MpuError> {
+        if !bytes.is_power_of_two() || bytes < 32 || bytes > 1 << 32 {
+            return Err(MpuError::InvalidSize);
+        }
+        // the region size is 2^(N + 1) bytes.
+        Ok(Self(bytes.trailing_zeros() - 1))
+    }
+
+    /// This function takes the size in bytes.
+    pub const fn bytes(&self) -> u64 {
+        1 << (self.0 + 1)
+    }
+
+    /// This function takes the encoding of the size field of DRSR.
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// MpuRegion
+//-------------------------------------------------------------------------------------------------
+
+/// The configuration of a MPU region.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MpuRegion {
+    /// The region number. Higher numbers take priority over lower ones.
+    pub number: u32,
+    pub base: u32,
+    pub size: RegionSize,
+    pub enabled: bool,
+    /// The raw DRACR value, which keeps encodings `RegionAttributes`
+    /// does not support.
+    pub access_control: u32,
+}
+
+impl MpuRegion {
+    /// This function decodes the attributes of the region.
+    pub fn attributes(&self) -> Option<RegionAttributes> {
+        RegionAttributes::from_bits(self.access_control)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Registers
+//-------------------------------------------------------------------------------------------------
+
+#[inline(always)]
+unsafe fn read_mpuir() -> u32 {
+    let rval;
+    asm!("mrc p15, 0, {}, c0, c0, 4", out(reg) rval, options(nomem, nostack));
+    rval
+}
+
+#[inline(always)]
+unsafe fn write_rgnr(v: u32) {
+    asm!("mcr p15, 0, {}, c6, c2, 0", "isb", in(reg) v, options(nostack));
+}
+
+#[inline(always)]
+unsafe fn read_drbar() -> u32 {
+    let rval;
+    asm!("mrc p15, 0, {}, c6, c1, 0", out(reg) rval, options(nostack));
+    rval
+}
+
+#[inline(always)]
+unsafe fn write_drbar(v: u32) {
+    asm!("mcr p15, 0, {}, c6, c1, 0", in(reg) v, options(nostack));
+}
+
+#[inline(always)]
+unsafe fn read_drsr() -> u32 {
+    let rval;
+    asm!("mrc p15, 0, {}, c6, c1, 2", out(reg) rval, options(nostack));
+    rval
+}
+
+#[inline(always)]
+unsafe fn write_drsr(v: u32) {
+    asm!("mcr p15, 0, {}, c6, c1, 2", in(reg) v, options(nostack));
+}
+
+#[inline(always)]
+unsafe fn read_dracr() -> u32 {
+    let rval;
+    asm!("mrc p15, 0, {}, c6, c1, 4", out(reg) rval, options(nostack));
+    rval
+}
+
+#[inline(always)]
+unsafe fn write_dracr(v: u32) {
+    asm!("mcr p15, 0, {}, c6, c1, 4", in(reg) v, options(nostack));
+}
+
+//-------------------------------------------------------------------------------------------------
+// MPU
+//-------------------------------------------------------------------------------------------------
+
+/// This function takes the number of MPU regions (12 or 16 on Cortex-R5).
+pub fn region_count() -> u32 {
+    unsafe { (read_mpuir() >> 8) & 0xff }
+}
+
+/// This function reads the configuration of the region.
+///
+/// # Errors
+/// If the number is out of the regions, it returns `InvalidRegion`.
+pub fn read_region(number: u32) -> Result<MpuRegion, MpuError> {
+    if number >= region_count() {
+        return Err(MpuError::InvalidRegion);
+    }
+
+    interrupt_free(|| unsafe {
+        write_rgnr(number);
+        let drsr = read_drsr();
+        Ok(MpuRegion {
+            number,
+            base: read_drbar(),
+            size: RegionSize((drsr >> DRSR_SIZE_SHIFT) & DRSR_SIZE_MASK),
+            enabled: drsr & DRSR_EN != 0,
+            access_control: read_dracr(),
+        })
+    })
+}
+
+fn validate(base: u32, size: u64) -> Result<RegionSize, MpuError> {
+    let size = RegionSize::from_bytes(size)?;
+    if base as u64 & (size.bytes() - 1) != 0 {
+        return Err(MpuError::Misaligned);
+    }
+    Ok(size)
+}
+
+/// This function configures and enables the region.
+///
+/// If regions overlap, the region of the highest number takes priority.
+/// The D-cache is flushed before the change,
+/// so changing cacheability does not lose data.
+/// The region is disabled while its base and attributes are rewritten,
+/// and the change is recorded in `Mpu_Config` of the BSP,
+/// so `add_region` does not take the region.
+///
+/// # Errors
+/// - If the size is not a power of two, it returns `InvalidSize`.
+/// - If the base is not aligned to the size, it returns `Misaligned`.
+/// - If the number is out of the regions, it returns `InvalidRegion`.
+///
+/// # Safety
+/// The new attributes must not make the running code, the stack
+/// or the data in use inaccessible.
+/// While the region is disabled, the code and the stack must stay
+/// accessible by the other regions.
+pub unsafe fn set_region(
+    number: u32,
+    base: u32,
+    size: u64,
+    attributes: RegionAttributes,
+) -> Result<(), MpuError> {
+    let size = validate(base, size)?;
+    if number >= region_count() {
+        return Err(MpuError::InvalidRegion);
+    }
+
+    let drsr = size.bits() << DRSR_SIZE_SHIFT | DRSR_EN;
+    interrupt_free(|| {
+        esys::Xil_DCacheFlush();
+        write_rgnr(number);
+        // the old size must not apply to the new base.
+        write_drsr(read_drsr() & !DRSR_EN);
+        asm!("dsb", "isb");
+        write_drbar(base);
+        write_dracr(attributes.bits());
+        write_drsr(drsr);
+        asm!("dsb", "isb");
+        esys::Xil_UpdateMPUConfig(number, base as _, drsr, attributes.bits());
+    });
+    Ok(())
+}
+
+/// This function configures the next free region by `Xil_SetMPURegion`.
+///
+/// # Errors
+/// - If the size is not a power of two, it returns `InvalidSize`.
+/// - If the base is not aligned to the size, it returns `Misaligned`.
+/// - If no region is free, it returns `NoFreeRegion`.
+///
+/// # Safety
+/// See `set_region`.
+pub unsafe fn add_region(
+    base: u32,
+    size: u64,
+    attributes: RegionAttributes,
+) -> Result<(), MpuError> {
+    validate(base, size)?;
+    match esys::Xil_SetMPURegion(base as _, size, attributes.bits()) {
+        0 => Ok(()),
+        _ => Err(MpuError::NoFreeRegion),
+    }
+}
+
+/// This function disables the region,
+/// and records it in `Mpu_Config` of the BSP, so `add_region` can reuse it.
+///
+/// # Errors
+/// If the number is out of the regions, it returns `InvalidRegion`.
+///
+/// # Safety
+/// See `set_region`.
+pub unsafe fn disable_region(number: u32) -> Result<(), MpuError> {
+    if number >= region_count() {
+        return Err(MpuError::InvalidRegion);
+    }
+
+    interrupt_free(|| {
+        esys::Xil_DCacheFlush();
+        write_rgnr(number);
+        let drsr = read_drsr() & !DRSR_EN;
+        write_drsr(drsr);
+        asm!("dsb", "isb");
+        esys::Xil_UpdateMPUConfig(
+            number,
+            read_drbar() as _,
+            drsr,
+            read_dracr(),
+        );
+    });
+    Ok(())
+}
+
+/// This function prints the configuration of all regions to the UART.
+pub fn dump() {
+    println!("MPU: {} regions", region_count());
+    for number in 0..region_count() {
+        if let Ok(region) = read_region(number) {
+            if !region.enabled {
+                println!("  [{:2}] disabled", number);
+                continue;
+            }
+            println!(
+                "  [{:2}] 0x{:08x} - 0x{:08x} DRACR 0x{:04x} {:?}",
+                number,
+                region.base,
+                region.base as u64 + region.size.bytes() - 1,
+                region.access_control,
+                region.attributes(),
+            );
+        }
+    }
+}
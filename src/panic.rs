@@ -0,0 +1,172 @@
+extern crate embeddedsw_sys;
+use core::{
+    arch::asm,
+    fmt::{self, Write},
+    panic::PanicInfo,
+    sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
+};
+#[cfg(not(feature = "semihosting"))]
+use embeddedsw_sys as esys;
+
+use crate::console::LineEnding;
+
+/// The reset control register of CRL_APB.
+const CRL_APB_RESET_CTRL: u32 = 0xff5e_0000 + 0x218;
+/// The bit which resets the whole system.
+const RESET_CTRL_SOFT_RESET: u32 = 0x10;
+
+/// The number of stack words which the handler prints.
+const STACK_DUMP_WORDS: usize = 16;
+
+//-------------------------------------------------------------------------------------------------
+// PanicPolicy
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for what the panic handler does after printing the message.
+#[derive(Copy, Clone)]
+pub enum PanicPolicy {
+    /// Halt the core in `wfi`. It is the default.
+    Halt,
+    /// Reset the whole system through CRL_APB.
+    Reset,
+    /// Call the hook, which never returns.
+    /// Interrupts are masked when it is called.
+    Hook(fn(&PanicInfo) -> !),
+}
+
+const POLICY_HALT: u8 = 0;
+const POLICY_RESET: u8 = 1;
+const POLICY_HOOK: u8 = 2;
+
+static POLICY: AtomicU8 = AtomicU8::new(POLICY_HALT);
+static HOOK: AtomicUsize = AtomicUsize::new(0);
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// This function selects the policy of the panic handler.
+///
+/// # Example
+/// ```
+/// use core::panic::PanicInfo;
+/// use embeddedsw_rs::panic::{self, PanicPolicy};
+///
+/// fn safe_state(_info: &PanicInfo) -> ! {
+///     // stop the motors
+///     loop {}
+/// }
+///
+/// panic::set_policy(PanicPolicy::Hook(safe_state));
+/// ```
+pub fn set_policy(policy: PanicPolicy) {
+    match policy {
+        PanicPolicy::Halt => POLICY.store(POLICY_HALT, Ordering::Release),
+        PanicPolicy::Reset => POLICY.store(POLICY_RESET, Ordering::Release),
+        PanicPolicy::Hook(hook) => {
+            HOOK.store(hook as usize, Ordering::Release);
+            POLICY.store(POLICY_HOOK, Ordering::Release);
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Panic handler
+//-------------------------------------------------------------------------------------------------
+
+/// The writer which sends bytes to the UART with `outbyte` directly,
+/// or to the host with the `semihosting` feature,
+/// so a panic never waits for a lock held by the interrupted code.
+struct PanicWriter;
+
+impl Write for PanicWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let crlf = crate::console::line_ending() == LineEnding::CrLf;
+        for c in s.bytes() {
+            if c == b'\n' && crlf {
+                put(b'\r');
+            }
+            put(c);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "semihosting"))]
+fn put(c: u8) {
+    unsafe { esys::outbyte(c as _) }
+}
+
+#[cfg(feature = "semihosting")]
+fn put(c: u8) {
+    crate::semihosting::write_char(c)
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    let lr: u32;
+    let sp: u32;
+    unsafe {
+        asm!("mov {}, lr", out(reg) lr, options(nomem, nostack));
+        asm!("mov {}, sp", out(reg) sp, options(nomem, nostack));
+        asm!("cpsid if", options(nomem, nostack));
+    }
+
+    // a panic while printing must not print again.
+    if PANICKING.swap(true, Ordering::AcqRel) {
+        let _ = writeln!(PanicWriter, "\npanicked while panicking");
+        halt();
+    }
+
+    // the output buffered before the panic comes first.
+    crate::console::panic_flush();
+
+    let mut w = PanicWriter;
+    let _ = writeln!(w, "\n*** panic ***");
+    if let Some(location) = info.location() {
+        let _ = writeln!(
+            w,
+            "at {}:{}:{}",
+            location.file(),
+            location.line(),
+            location.column()
+        );
+    }
+    let _ = writeln!(w, "{}", info.message());
+    // the panic handler is called through libcore,
+    // so LR points into libcore rather than to the panicking code.
+    let _ = writeln!(w, "panic handler: LR 0x{:08x} SP 0x{:08x}", lr, sp);
+    for i in 0..STACK_DUMP_WORDS {
+        let addr = sp as usize + i * 4;
+        let word = unsafe { core::ptr::read_volatile(addr as *const u32) };
+        let _ =
+            write!(w, "{:08x}{}", word, if i % 4 == 3 { "\n" } else { " " });
+    }
+    #[cfg(feature = "crash-record")]
+    crate::crash::record_panic(info, lr, sp);
+
+    match POLICY.load(Ordering::Acquire) {
+        POLICY_RESET => reset(),
+        POLICY_HOOK => {
+            let hook: fn(&PanicInfo) -> ! =
+                unsafe { core::mem::transmute(HOOK.load(Ordering::Acquire)) };
+            hook(info)
+        }
+        _ => halt(),
+    }
+}
+
+fn halt() -> ! {
+    loop {
+        unsafe { asm!("dsb", "wfi", options(nomem, nostack)) };
+    }
+}
+
+fn reset() -> ! {
+    unsafe {
+        let ctrl = CRL_APB_RESET_CTRL as *mut u32;
+        core::ptr::write_volatile(
+            ctrl,
+            core::ptr::read_volatile(ctrl) | RESET_CTRL_SOFT_RESET,
+        );
+        asm!("dsb", options(nostack));
+    }
+    halt()
+}
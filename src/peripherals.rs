@@ -0,0 +1,328 @@
+extern crate embeddedsw_sys;
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use embeddedsw_sys as esys;
+
+use crate::xscugic::{XScuGic, XScuGicConfig};
+use crate::xstatus::XStatus;
+
+#[cfg(feature = "xilffs")]
+use crate::ff::{FResult, FatFs, FileMountOption};
+#[cfg(feature = "xaxidma")]
+use crate::xaxidma::{DmaError, XAxiDma, XAxiDmaConfig};
+#[cfg(feature = "xipipsu")]
+use crate::xipipsu::{XIpiPsu, XIpiPsuConfig};
+#[cfg(feature = "xuartps")]
+use crate::xuartps::{XUartPs, XUartPsConfig};
+
+//-------------------------------------------------------------------------------------------------
+// PeripheralError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for errors of taking peripherals.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PeripheralError {
+    /// The device has already been taken.
+    AlreadyTaken,
+    /// The device ID is not found in the hardware design.
+    NotFound,
+    /// The driver failed to initialize the device with the XST_* status code.
+    Init(XStatus),
+    /// The DMA driver failed to initialize the device.
+    #[cfg(feature = "xaxidma")]
+    Dma(DmaError),
+    /// FatFs failed to mount the logical drive.
+    #[cfg(feature = "xilffs")]
+    Mount(FResult),
+}
+
+impl core::fmt::Display for PeripheralError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PeripheralError::AlreadyTaken => {
+                write!(f, "the device has already been taken")
+            }
+            PeripheralError::NotFound => write!(f, "the device is not found"),
+            PeripheralError::Init(status) => {
+                write!(f, "failed to initialize the device: {}", status)
+            }
+            #[cfg(feature = "xaxidma")]
+            PeripheralError::Dma(e) => {
+                write!(f, "failed to initialize the DMA: {}", e)
+            }
+            #[cfg(feature = "xilffs")]
+            PeripheralError::Mount(e) => {
+                write!(f, "failed to mount the drive: {}", e)
+            }
+        }
+    }
+}
+
+impl core::error::Error for PeripheralError {}
+
+//-------------------------------------------------------------------------------------------------
+// Storage
+//-------------------------------------------------------------------------------------------------
+
+/// Static storage of driver instances.
+/// Each slot is written only once by `Peripherals`,
+/// which guarantees that only one reference to it exists.
+struct Storage<T, const N: usize> {
+    slots: UnsafeCell<[MaybeUninit<T>; N]>,
+}
+
+unsafe impl<T, const N: usize> Sync for Storage<T, N> {}
+
+impl<T, const N: usize> Storage<T, N> {
+    const fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new([const { MaybeUninit::uninit() }; N]),
+        }
+    }
+
+    /// # Safety
+    /// Each index must be used only once.
+    unsafe fn slot(&self, index: usize) -> &'static mut MaybeUninit<T> {
+        &mut (*(self.slots.get() as *mut [MaybeUninit<T>; N]))[index]
+    }
+}
+
+const SCUGIC_NUM: usize = esys::XPAR_XSCUGIC_NUM_INSTANCES as usize;
+static SCUGIC: Storage<XScuGic, SCUGIC_NUM> = Storage::new();
+
+#[cfg(feature = "xaxidma")]
+const AXIDMA_NUM: usize = esys::XPAR_XAXIDMA_NUM_INSTANCES as usize;
+#[cfg(feature = "xaxidma")]
+static AXIDMA: Storage<XAxiDma, AXIDMA_NUM> = Storage::new();
+
+#[cfg(feature = "xipipsu")]
+const IPIPSU_NUM: usize = esys::XPAR_XIPIPSU_NUM_INSTANCES as usize;
+#[cfg(feature = "xipipsu")]
+static IPIPSU: Storage<XIpiPsu, IPIPSU_NUM> = Storage::new();
+
+#[cfg(feature = "xuartps")]
+const UARTPS_NUM: usize = esys::XPAR_XUARTPS_NUM_INSTANCES as usize;
+#[cfg(feature = "xuartps")]
+static UARTPS: Storage<XUartPs, UARTPS_NUM> = Storage::new();
+
+#[cfg(feature = "xilffs")]
+const FATFS_NUM: usize = esys::FF_VOLUMES as usize;
+#[cfg(feature = "xilffs")]
+static FATFS: Storage<FatFs, FATFS_NUM> = Storage::new();
+#[cfg(feature = "xilffs")]
+const FATFS_PATHS: [&str; 10] = [
+    "0:/\0", "1:/\0", "2:/\0", "3:/\0", "4:/\0", "5:/\0", "6:/\0", "7:/\0",
+    "8:/\0", "9:/\0",
+];
+
+//-------------------------------------------------------------------------------------------------
+// Peripherals
+//-------------------------------------------------------------------------------------------------
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// The owner of all peripherals.
+///
+/// `Peripherals::take` returns it only once, and it hands out
+/// one initialized driver instance per hardware instance.
+/// The instances live in static storage, so the caller does not have to
+/// manage `MaybeUninit` and two instances never drive the same hardware.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{peripherals::Peripherals, raw};
+///
+/// let mut p = Peripherals::take().unwrap();
+/// let xscu_gic = p.scugic(raw::XPAR_SCUGIC_0_DEVICE_ID as u16).unwrap();
+/// xscu_gic.exception_register_handler();
+///
+/// // the same device cannot be taken twice
+/// assert!(p.scugic(raw::XPAR_SCUGIC_0_DEVICE_ID as u16).is_err());
+/// ```
+pub struct Peripherals {
+    scugic: u32,
+    #[cfg(feature = "xaxidma")]
+    axidma: u32,
+    #[cfg(feature = "xipipsu")]
+    ipipsu: u32,
+    #[cfg(feature = "xuartps")]
+    uartps: u32,
+    #[cfg(feature = "xilffs")]
+    fatfs: u32,
+}
+
+impl Peripherals {
+    /// This function takes the peripherals.
+    /// It returns `Some` only at the first call.
+    pub fn take() -> Option<Self> {
+        if TAKEN.swap(true, Ordering::AcqRel) {
+            None
+        } else {
+            Some(unsafe { Self::steal() })
+        }
+    }
+
+    /// This function takes the peripherals without checking
+    /// whether they have already been taken.
+    ///
+    /// # Safety
+    /// The devices taken through the returned value may alias
+    /// the ones taken through another `Peripherals`.
+    pub unsafe fn steal() -> Self {
+        TAKEN.store(true, Ordering::Release);
+        Self {
+            scugic: 0,
+            #[cfg(feature = "xaxidma")]
+            axidma: 0,
+            #[cfg(feature = "xipipsu")]
+            ipipsu: 0,
+            #[cfg(feature = "xuartps")]
+            uartps: 0,
+            #[cfg(feature = "xilffs")]
+            fatfs: 0,
+        }
+    }
+
+    /// This function marks the index as taken.
+    fn claim(
+        taken: &mut u32,
+        index: usize,
+        num: usize,
+    ) -> Result<(), PeripheralError> {
+        if index >= num {
+            return Err(PeripheralError::NotFound);
+        }
+        if *taken & (1 << index) != 0 {
+            return Err(PeripheralError::AlreadyTaken);
+        }
+        *taken |= 1 << index;
+        Ok(())
+    }
+
+    /// This function takes an initialized XScuGic instance.
+    ///
+    /// # Errors
+    /// - If the device has already been taken, it returns `AlreadyTaken`.
+    /// - If the device is not found, it returns `NotFound`.
+    /// - If the driver fails to initialize the device, it returns `Init`.
+    pub fn scugic(
+        &mut self,
+        device_id: u16,
+    ) -> Result<&'static mut XScuGic, PeripheralError> {
+        let config = XScuGicConfig::lookup_config(device_id)
+            .map_err(|_| PeripheralError::NotFound)?;
+        let index = device_id as usize;
+        Self::claim(&mut self.scugic, index, SCUGIC_NUM)?;
+
+        let slot = unsafe { SCUGIC.slot(index) };
+        // the R5 has no MMU, so the effective address is the physical one.
+        let status = unsafe {
+            XScuGic::cfg_initialize(slot, &config, config.get_cpu_base_addr())
+        };
+        if let Err(status) = status {
+            self.scugic &= !(1 << index);
+            return Err(PeripheralError::Init(status));
+        }
+        Ok(unsafe { slot.assume_init_mut() })
+    }
+
+    /// This function takes an initialized XAxiDma instance.
+    ///
+    /// # Errors
+    /// - If the device has already been taken, it returns `AlreadyTaken`.
+    /// - If the device is not found, it returns `NotFound`.
+    /// - If the driver fails to initialize the device, it returns `Dma`.
+    #[cfg(feature = "xaxidma")]
+    pub fn axidma(
+        &mut self,
+        device_id: u32,
+    ) -> Result<&'static mut XAxiDma, PeripheralError> {
+        let mut config = XAxiDmaConfig::lookup_config(device_id)
+            .map_err(|_| PeripheralError::NotFound)?;
+        let index = device_id as usize;
+        Self::claim(&mut self.axidma, index, AXIDMA_NUM)?;
+
+        let slot = unsafe { AXIDMA.slot(index) };
+        if let Err(e) = XAxiDma::cfg_initialize(slot, &mut config) {
+            self.axidma &= !(1 << index);
+            return Err(PeripheralError::Dma(e));
+        }
+        Ok(unsafe { slot.assume_init_mut() })
+    }
+
+    /// This function takes an initialized XIpiPsu instance.
+    ///
+    /// # Errors
+    /// - If the device has already been taken, it returns `AlreadyTaken`.
+    /// - If the device is not found, it returns `NotFound`.
+    /// - If the driver fails to initialize the device, it returns `Init`.
+    #[cfg(feature = "xipipsu")]
+    pub fn ipipsu(
+        &mut self,
+        device_id: u32,
+    ) -> Result<&'static mut XIpiPsu, PeripheralError> {
+        let config = XIpiPsuConfig::lookup_config(device_id)
+            .map_err(|_| PeripheralError::NotFound)?;
+        let index = device_id as usize;
+        Self::claim(&mut self.ipipsu, index, IPIPSU_NUM)?;
+
+        let slot = unsafe { IPIPSU.slot(index) };
+        if let Err(status) = XIpiPsu::cfg_initialize(slot, &config) {
+            self.ipipsu &= !(1 << index);
+            return Err(PeripheralError::Init(status));
+        }
+        Ok(unsafe { slot.assume_init_mut() })
+    }
+
+    /// This function takes an initialized XUartPs instance.
+    ///
+    /// # Errors
+    /// - If the device has already been taken, it returns `AlreadyTaken`.
+    /// - If the device is not found, it returns `NotFound`.
+    /// - If the driver fails to initialize the device, it returns `Init`.
+    #[cfg(feature = "xuartps")]
+    pub fn uartps(
+        &mut self,
+        device_id: u16,
+    ) -> Result<&'static mut XUartPs, PeripheralError> {
+        let config = XUartPsConfig::lookup_config(device_id)
+            .map_err(|_| PeripheralError::NotFound)?;
+        let index = device_id as usize;
+        Self::claim(&mut self.uartps, index, UARTPS_NUM)?;
+
+        let slot = unsafe { UARTPS.slot(index) };
+        if let Err(status) = XUartPs::cfg_initialize(slot, &config) {
+            self.uartps &= !(1 << index);
+            return Err(PeripheralError::Init(status));
+        }
+        Ok(unsafe { slot.assume_init_mut() })
+    }
+
+    /// This function mounts the logical drive and takes the FatFs instance.
+    ///
+    /// # Errors
+    /// - If the drive has already been mounted, it returns `AlreadyTaken`.
+    /// - If the drive number is out of `FF_VOLUMES`, it returns `NotFound`.
+    /// - If FatFs fails to mount the drive, it returns `Mount`.
+    #[cfg(feature = "xilffs")]
+    pub fn fatfs(
+        &mut self,
+        drive: u8,
+        opt: FileMountOption,
+    ) -> Result<&'static mut FatFs, PeripheralError> {
+        let index = drive as usize;
+        Self::claim(&mut self.fatfs, index, FATFS_NUM.min(FATFS_PATHS.len()))?;
+
+        let slot = unsafe { FATFS.slot(index) };
+        if let Err(fresult) =
+            unsafe { FatFs::mount(slot, FATFS_PATHS[index], opt) }
+        {
+            self.fatfs &= !(1 << index);
+            return Err(PeripheralError::Mount(fresult));
+        }
+        Ok(unsafe { slot.assume_init_mut() })
+    }
+}
@@ -0,0 +1,206 @@
+extern crate embeddedsw_sys;
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    mem::MaybeUninit,
+    ptr::NonNull,
+};
+use embeddedsw_sys as esys;
+
+use crate::heap::{Heap, HeapStats};
+use crate::xscugic::Mutex;
+
+//-------------------------------------------------------------------------------------------------
+// Regions
+//-------------------------------------------------------------------------------------------------
+
+/// The size of each TCM bank in split mode.
+#[cfg(not(feature = "tcm-lockstep"))]
+const TCM_BANK_SIZE: usize = 0x1_0000;
+/// The size of each TCM bank in lockstep mode,
+/// where the R5 core uses the banks of both cores.
+#[cfg(feature = "tcm-lockstep")]
+const TCM_BANK_SIZE: usize = 0x2_0000;
+/// The offset of BTCM from ATCM in the R5 address map.
+const BTCM_OFFSET: usize = 0x2_0000;
+
+/// The ATCM of the R5 core
+/// (64 KiB, or 128 KiB with the `tcm-lockstep` feature).
+///
+/// ATCM starts at address 0, which the heap never hands out,
+/// so memory passed to `init` from address 0 loses its first bytes
+/// (see `Heap::init`).
+pub static ATCM: RegionHeap = RegionHeap::new(
+    "ATCM",
+    esys::XPAR_PSU_R5_TCM_RAM_0_S_AXI_BASEADDR as usize,
+    TCM_BANK_SIZE,
+);
+
+/// The BTCM of the R5 core
+/// (64 KiB, or 128 KiB with the `tcm-lockstep` feature).
+pub static BTCM: RegionHeap = RegionHeap::new(
+    "BTCM",
+    esys::XPAR_PSU_R5_TCM_RAM_0_S_AXI_BASEADDR as usize + BTCM_OFFSET,
+    TCM_BANK_SIZE,
+);
+
+/// The on-chip memory (256 KiB).
+pub static OCM: RegionHeap = RegionHeap::new(
+    "OCM",
+    esys::XPAR_PSU_OCM_RAM_0_S_AXI_BASEADDR as usize,
+    (esys::XPAR_PSU_OCM_RAM_0_S_AXI_HIGHADDR
+        - esys::XPAR_PSU_OCM_RAM_0_S_AXI_BASEADDR) as usize
+        + 1,
+);
+
+/// The DDR which the R5 core can access.
+pub static DDR: RegionHeap = RegionHeap::new(
+    "DDR",
+    esys::XPAR_PSU_R5_DDR_0_S_AXI_BASEADDR as usize,
+    (esys::XPAR_PSU_R5_DDR_0_S_AXI_HIGHADDR
+        - esys::XPAR_PSU_R5_DDR_0_S_AXI_BASEADDR) as usize
+        + 1,
+);
+
+//-------------------------------------------------------------------------------------------------
+// RegionError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for errors of region heaps.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RegionError {
+    /// The memory is not inside the region.
+    OutOfRegion,
+}
+
+//-------------------------------------------------------------------------------------------------
+// RegionHeap
+//-------------------------------------------------------------------------------------------------
+
+/// A heap which allocates only from one memory region.
+///
+/// The code and data of the application are also placed in the regions,
+/// so the heap manages only the memory passed to `init`,
+/// which must be inside the region.
+/// It implements `Allocator`, so it can be used with `Box::new_in` and
+/// `Vec::new_in`. Allocations take bounded time (see `heap::Heap`).
+///
+/// # Example
+/// ```
+/// extern crate alloc;
+/// use alloc::{boxed::Box, vec::Vec};
+/// use core::mem::MaybeUninit;
+/// use embeddedsw_rs::region::{ATCM, DDR};
+///
+/// #[link_section = ".atcm"]
+/// static mut ATCM_POOL: [MaybeUninit<u8>; 0x2000] = [MaybeUninit::uninit(); 0x2000];
+///
+/// ATCM.init_from_slice(unsafe { &mut *core::ptr::addr_of_mut!(ATCM_POOL) }).unwrap();
+/// unsafe { DDR.init(0x1000_0000, 0x0100_0000) }.unwrap();
+///
+/// let state = Box::new_in([0u32; 16], &ATCM);
+/// let mut samples = Vec::with_capacity_in(0x10_0000, &DDR);
+/// samples.push(0u16);
+/// ```
+pub struct RegionHeap {
+    name: &'static str,
+    base: usize,
+    size: usize,
+    heap: Mutex<Heap>,
+}
+
+impl RegionHeap {
+    /// This function creates a heap of the region,
+    /// which has no memory to allocate yet.
+    pub const fn new(name: &'static str, base: usize, size: usize) -> Self {
+        Self {
+            name,
+            base,
+            size,
+            heap: Mutex::new(Heap::empty()),
+        }
+    }
+
+    /// This function takes the name of the region.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// This function takes the base address of the region.
+    pub fn base_addr(&self) -> usize {
+        self.base
+    }
+
+    /// This function takes the size of the region.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// This function checks whether the memory is inside the region.
+    pub fn contains(&self, start: usize, size: usize) -> bool {
+        start >= self.base
+            && size <= self.size
+            && start - self.base <= self.size - size
+    }
+
+    /// This function adds the memory to the heap.
+    ///
+    /// # Errors
+    /// If the memory is not inside the region, it returns `OutOfRegion`.
+    ///
+    /// # Safety
+    /// See `Heap::init`.
+    pub unsafe fn init(
+        &self,
+        start: usize,
+        size: usize,
+    ) -> Result<(), RegionError> {
+        if !self.contains(start, size) {
+            return Err(RegionError::OutOfRegion);
+        }
+        self.heap.lock(|heap| heap.init(start, size));
+        Ok(())
+    }
+
+    /// This function adds the static buffer to the heap.
+    ///
+    /// # Errors
+    /// If the buffer is not inside the region, it returns `OutOfRegion`.
+    /// Place the buffer with `#[link_section]`.
+    pub fn init_from_slice(
+        &self,
+        mem: &'static mut [MaybeUninit<u8>],
+    ) -> Result<(), RegionError> {
+        if !self.contains(mem.as_ptr() as usize, mem.len()) {
+            return Err(RegionError::OutOfRegion);
+        }
+        self.heap.lock(|heap| heap.init_from_slice(mem));
+        Ok(())
+    }
+
+    /// This function takes the statistics of the heap.
+    pub fn stats(&self) -> HeapStats {
+        self.heap.lock(|heap| heap.stats())
+    }
+}
+
+unsafe impl Allocator for RegionHeap {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if layout.size() == 0 {
+            // a zero-sized allocation does not take memory.
+            let dangling =
+                unsafe { NonNull::new_unchecked(layout.align() as *mut u8) };
+            return Ok(NonNull::slice_from_raw_parts(dangling, 0));
+        }
+
+        self.heap
+            .lock(|heap| heap.allocate(layout))
+            .map(|ptr| NonNull::slice_from_raw_parts(ptr, layout.size()))
+            .ok_or(AllocError)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if layout.size() != 0 {
+            self.heap.lock(|heap| heap.deallocate(ptr))
+        }
+    }
+}
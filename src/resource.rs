@@ -0,0 +1,255 @@
+use crate::xscugic::XScuGic;
+use core::{
+    arch::asm,
+    cell::UnsafeCell,
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+//-------------------------------------------------------------------------------------------------
+// Priority
+//-------------------------------------------------------------------------------------------------
+
+/// The lowest priority, which is used for the idle loop (main).
+pub const IDLE_PRIORITY: u8 = 0xff;
+
+/// The step between two priority levels of the GIC.
+/// The GIC implements only upper 5 bits of priorities.
+pub const PRIORITY_STEP: u8 = 8;
+
+/// The address of the priority mask register of the CPU interface.
+static PRIORITY_MASK_ADDR: AtomicU32 = AtomicU32::new(0);
+
+/// A token which proves that the code runs at the priority P.
+///
+/// Lower values mean higher priorities as in the GIC.
+/// Tokens are created by the handlers which `gic_tasks!` generates,
+/// and they cannot be moved to another context.
+pub struct Priority<const P: u8> {
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<const P: u8> Priority<P> {
+    /// This function creates a token of the priority P.
+    ///
+    /// # Safety
+    /// The caller must run at the priority P,
+    /// that is, in the handler of an interrupt which has the priority P,
+    /// or in the idle loop if P is `IDLE_PRIORITY`.
+    #[inline(always)]
+    pub unsafe fn new() -> Self {
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+}
+
+/// This function prepares resource locking with the GIC.
+/// `gic_tasks!` calls it, so you do not have to call it
+/// unless you create `Priority` tokens by yourself.
+pub fn init(gic: &XScuGic) {
+    PRIORITY_MASK_ADDR.store(
+        gic.cpu_base_addr() + embeddedsw_sys::XSCUGIC_CPU_PRIOR_OFFSET,
+        Ordering::Release,
+    );
+}
+
+#[inline(always)]
+unsafe fn read_priority_mask(addr: u32) -> u8 {
+    core::ptr::read_volatile(addr as *const u32) as u8
+}
+
+#[inline(always)]
+unsafe fn write_priority_mask(addr: u32, mask: u8) {
+    core::ptr::write_volatile(addr as *mut u32, mask as u32);
+    // the new mask must take effect before touching the resource.
+    asm!("dsb", "isb");
+}
+
+//-------------------------------------------------------------------------------------------------
+// Resource
+//-------------------------------------------------------------------------------------------------
+
+/// Data shared between tasks, protected by the priority ceiling protocol.
+///
+/// CEILING is the highest priority (the lowest value) of the tasks
+/// which access the resource.
+/// The interrupt handler of the BSP runs the tasks with IRQ masked,
+/// so tasks do not preempt each other, and only the idle loop is
+/// preempted by them. Locking the resource below the ceiling raises
+/// the GIC priority mask to the ceiling, so the tasks which use it stay
+/// pending until the lock is released, while the interrupts of higher
+/// priorities than the ceiling are still taken.
+/// A task whose priority is higher than the ceiling cannot lock the resource,
+/// and it is reported at compile time.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::resource::{Priority, Resource};
+///
+/// // shared by the idle loop and the task of the priority 0xa0
+/// static COUNTER: Resource<u32, 0xa0> = Resource::new(0);
+///
+/// fn uart_rx(prio: &Priority<0xa0>) {
+///     COUNTER.lock(prio, |counter| *counter += 1);
+/// }
+///
+/// fn timer(prio: &Priority<0x80>) {
+///     // compile error: the priority 0x80 is higher than the ceiling 0xa0
+///     // COUNTER.lock(prio, |counter| *counter = 0);
+/// }
+/// ```
+pub struct Resource<T, const CEILING: u8> {
+    inner: UnsafeCell<T>,
+    locked: AtomicBool,
+}
+
+unsafe impl<T: Send, const CEILING: u8> Sync for Resource<T, CEILING> {}
+
+impl<T, const CEILING: u8> Resource<T, CEILING> {
+    /// This function creates a resource.
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: UnsafeCell::new(value),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// This function runs the closure with exclusive access to the data.
+    ///
+    /// If the task has the same priority as the ceiling, no other user
+    /// can run until it returns, so the priority mask is not changed.
+    ///
+    /// # Panics
+    /// If `lock` of the same resource is called in the closure, it panics.
+    pub fn lock<const P: u8, F, R>(&self, _prio: &Priority<P>, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        const {
+            assert!(
+                P >= CEILING,
+                "the task priority is higher than the resource ceiling"
+            )
+        };
+
+        if P == CEILING {
+            return self.access(f);
+        }
+
+        let addr = PRIORITY_MASK_ADDR.load(Ordering::Acquire);
+        assert!(addr != 0, "resource::init is not called");
+
+        unsafe {
+            let prev = read_priority_mask(addr);
+            if prev > CEILING {
+                write_priority_mask(addr, CEILING);
+            }
+            let r = self.access(f);
+            if prev > CEILING {
+                write_priority_mask(addr, prev);
+            }
+            r
+        }
+    }
+
+    /// This function hands out the data to one closure at a time.
+    /// The other users are masked by the caller, so only the closure
+    /// itself can lock the resource again.
+    fn access<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        if self.locked.swap(true, Ordering::Acquire) {
+            panic!("Resource is locked recursively");
+        }
+        let r = f(unsafe { &mut *self.inner.get() });
+        self.locked.store(false, Ordering::Release);
+        r
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// gic_tasks!
+//-------------------------------------------------------------------------------------------------
+
+/// Defines tasks bound to GIC interrupts and a function which sets them up.
+///
+/// Each task is a function which takes `&Priority<P>`.
+/// The generated function configures the priority and the trigger type,
+/// connects and enables each interrupt, and returns the token of the idle loop.
+/// `trigger` is optional and defaults to `Trigger::Level`.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{
+///     gic_tasks,
+///     resource::{Priority, Resource},
+///     xscugic::{Interrupt, Trigger},
+/// };
+///
+/// static SAMPLES: Resource<[u16; 16], 0xa0> = Resource::new([0; 16]);
+///
+/// gic_tasks! {
+///     fn setup_tasks;
+///     task adc(id = Interrupt::FabricAdc0, priority = 0xa0) => on_adc;
+///     task tick(id = Interrupt::Ttc00, priority = 0x80, trigger = Trigger::RisingEdge) => on_tick;
+/// }
+///
+/// fn on_adc(prio: &Priority<0xa0>) {
+///     SAMPLES.lock(prio, |samples| samples[0] = 1);
+/// }
+///
+/// fn on_tick(_prio: &Priority<0x80>) {}
+///
+/// // in main
+/// let idle = setup_tasks(&mut xscu_gic).unwrap();
+/// SAMPLES.lock(&idle, |samples| println!("{}", samples[0]));
+/// ```
+#[macro_export]
+macro_rules! gic_tasks {
+    (
+        fn $setup:ident;
+        $(
+            task $name:ident(
+                id = $id:expr,
+                priority = $prio:literal
+                $(, trigger = $trigger:expr)?
+            ) => $handler:path;
+        )*
+    ) => {
+        fn $setup(
+            gic: &mut $crate::xscugic::XScuGic,
+        ) -> Result<
+            $crate::resource::Priority<{ $crate::resource::IDLE_PRIORITY }>,
+            $crate::xstatus::XStatus,
+        > {
+            $crate::resource::init(gic);
+
+            $(
+                const _: () = assert!(
+                    $prio % $crate::resource::PRIORITY_STEP == 0
+                        && $prio < $crate::resource::IDLE_PRIORITY,
+                    "the priority must be a multiple of 8 and lower than the idle loop",
+                );
+
+                extern "C" fn $name() {
+                    let prio = unsafe {
+                        $crate::resource::Priority::<$prio>::new()
+                    };
+                    $handler(&prio);
+                }
+
+                #[allow(unused_mut, unused_assignments)]
+                let mut trigger = $crate::xscugic::Trigger::Level;
+                $(trigger = $trigger;)?
+
+                gic.set_priority_trigger_type($id, $prio, trigger);
+                gic.connect($id, Some($name))?;
+                gic.enable($id);
+            )*
+
+            Ok(unsafe { $crate::resource::Priority::new() })
+        }
+    };
+}
@@ -0,0 +1,284 @@
+use core::{
+    arch::asm,
+    ffi::CStr,
+    sync::atomic::{AtomicIsize, Ordering},
+};
+
+// Operation numbers of ARM semihosting.
+const SYS_OPEN: u32 = 0x01;
+const SYS_CLOSE: u32 = 0x02;
+const SYS_WRITEC: u32 = 0x03;
+const SYS_WRITE0: u32 = 0x04;
+const SYS_WRITE: u32 = 0x05;
+const SYS_READ: u32 = 0x06;
+const SYS_READC: u32 = 0x07;
+const SYS_SEEK: u32 = 0x0a;
+const SYS_FLEN: u32 = 0x0c;
+const SYS_REMOVE: u32 = 0x0e;
+const SYS_ERRNO: u32 = 0x13;
+const SYS_EXIT: u32 = 0x18;
+const SYS_EXIT_EXTENDED: u32 = 0x20;
+
+const ADP_STOPPED_APPLICATION_EXIT: usize = 0x20026;
+const ADP_STOPPED_RUN_TIME_ERROR_UNKNOWN: usize = 0x20023;
+
+/// This function calls the semihosting operation.
+///
+/// The debugger or QEMU (`-semihosting`) handles `svc 0x123456`.
+/// Without them, the SVC exception handler of the BSP runs instead.
+///
+/// # Safety
+/// The argument must be valid for the operation.
+pub unsafe fn syscall(op: u32, arg: usize) -> isize {
+    let r: isize;
+    asm!(
+        "svc 0x123456",
+        inout("r0") op as isize => r,
+        in("r1") arg,
+        options(nostack)
+    );
+    r
+}
+
+//-------------------------------------------------------------------------------------------------
+// Error
+//-------------------------------------------------------------------------------------------------
+
+/// An error of the host, which has the `errno` of the host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub errno: i32,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "semihosting error (errno {})", self.errno)
+    }
+}
+
+impl core::error::Error for Error {}
+
+fn last_error() -> Error {
+    Error {
+        errno: unsafe { syscall(SYS_ERRNO, 0) } as i32,
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Console
+//-------------------------------------------------------------------------------------------------
+
+/// The handles of `:tt`, which is the console of the host (-1 if not opened).
+static STDOUT: AtomicIsize = AtomicIsize::new(-1);
+static STDERR: AtomicIsize = AtomicIsize::new(-1);
+
+fn console_handle(handle: &AtomicIsize, mode: OpenMode) -> Option<isize> {
+    let h = handle.load(Ordering::Relaxed);
+    if h >= 0 {
+        return Some(h);
+    }
+    let h = open_raw(c":tt", mode).ok()?;
+    handle.store(h, Ordering::Relaxed);
+    Some(h)
+}
+
+/// This function writes the bytes to the console of the host.
+/// The console backend calls it with the `semihosting` feature.
+pub fn write_stdout(bytes: &[u8]) {
+    match console_handle(&STDOUT, OpenMode::Write) {
+        Some(h) => {
+            let _ = write_raw(h, bytes);
+        }
+        None => bytes.iter().for_each(|b| write_char(*b)),
+    }
+}
+
+/// This function writes the bytes to the error console of the host.
+pub fn write_stderr(bytes: &[u8]) {
+    match console_handle(&STDERR, OpenMode::Append) {
+        Some(h) => {
+            let _ = write_raw(h, bytes);
+        }
+        None => write_stdout(bytes),
+    }
+}
+
+/// This function writes a byte to the console of the host.
+pub fn write_char(c: u8) {
+    unsafe { syscall(SYS_WRITEC, &c as *const u8 as usize) };
+}
+
+/// This function writes the string to the console of the host.
+pub fn write_str0(s: &CStr) {
+    unsafe { syscall(SYS_WRITE0, s.as_ptr() as usize) };
+}
+
+/// This function waits for a byte from the console of the host.
+pub fn read_char() -> u8 {
+    unsafe { syscall(SYS_READC, 0) as u8 }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Exit
+//-------------------------------------------------------------------------------------------------
+
+/// This function stops the program with the exit code,
+/// so QEMU exits with it.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{panic::{self, PanicPolicy}, semihosting};
+///
+/// // a failed test makes QEMU exit with 1.
+/// panic::set_policy(PanicPolicy::Hook(|_| semihosting::exit(1)));
+/// // run the tests
+/// semihosting::exit(0);
+/// ```
+pub fn exit(code: i32) -> ! {
+    unsafe {
+        if code == 0 {
+            syscall(SYS_EXIT, ADP_STOPPED_APPLICATION_EXIT);
+        } else {
+            let block = [ADP_STOPPED_APPLICATION_EXIT, code as usize];
+            syscall(SYS_EXIT_EXTENDED, block.as_ptr() as usize);
+            // the host does not support the extended exit.
+            syscall(SYS_EXIT, ADP_STOPPED_RUN_TIME_ERROR_UNKNOWN);
+        }
+    }
+    loop {
+        unsafe { asm!("wfi", options(nomem, nostack)) };
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// File
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the modes of `fopen` of the host.
+#[repr(usize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum OpenMode {
+    /// "rb"
+    Read = 1,
+    /// "r+b"
+    ReadWrite = 3,
+    /// "wb"
+    Write = 5,
+    /// "w+b"
+    WriteRead = 7,
+    /// "ab"
+    Append = 9,
+    /// "a+b"
+    AppendRead = 11,
+}
+
+fn open_raw(path: &CStr, mode: OpenMode) -> Result<isize, Error> {
+    let args = [path.as_ptr() as usize, mode as usize, path.count_bytes()];
+    let h = unsafe { syscall(SYS_OPEN, args.as_ptr() as usize) };
+    if h < 0 {
+        Err(last_error())
+    } else {
+        Ok(h)
+    }
+}
+
+fn write_raw(handle: isize, bytes: &[u8]) -> Result<(), Error> {
+    let args = [handle as usize, bytes.as_ptr() as usize, bytes.len()];
+    // it returns the number of bytes which are not written.
+    match unsafe { syscall(SYS_WRITE, args.as_ptr() as usize) } {
+        0 => Ok(()),
+        _ => Err(last_error()),
+    }
+}
+
+/// A file of the host.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::semihosting::{File, OpenMode};
+///
+/// let mut file = File::open(c"result.bin", OpenMode::Write).unwrap();
+/// file.write(&[1, 2, 3]).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct File {
+    handle: isize,
+}
+
+impl File {
+    /// This function opens the file of the host.
+    /// The path is relative to the working directory of the host.
+    ///
+    /// # Errors
+    /// If the file cannot be opened, it returns the errno of the host.
+    pub fn open(path: &CStr, mode: OpenMode) -> Result<File, Error> {
+        open_raw(path, mode).map(|handle| File { handle })
+    }
+
+    /// This function writes all bytes.
+    ///
+    /// # Errors
+    /// If the bytes cannot be written, it returns the errno of the host.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        write_raw(self.handle, bytes)
+    }
+
+    /// This function reads bytes into the buffer,
+    /// and takes the number of bytes read. 0 means the end of the file.
+    ///
+    /// # Errors
+    /// If the bytes cannot be read, it returns the errno of the host.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let args = [self.handle as usize, buf.as_mut_ptr() as usize, buf.len()];
+        // it returns the number of bytes which are not read.
+        let rest = unsafe { syscall(SYS_READ, args.as_ptr() as usize) };
+        if rest < 0 || rest as usize > buf.len() {
+            Err(last_error())
+        } else {
+            Ok(buf.len() - rest as usize)
+        }
+    }
+
+    /// This function moves the position to the offset from the start.
+    ///
+    /// # Errors
+    /// If the position cannot be moved, it returns the errno of the host.
+    pub fn seek(&mut self, offset: usize) -> Result<(), Error> {
+        let args = [self.handle as usize, offset];
+        match unsafe { syscall(SYS_SEEK, args.as_ptr() as usize) } {
+            0 => Ok(()),
+            _ => Err(last_error()),
+        }
+    }
+
+    /// This function takes the length of the file.
+    ///
+    /// # Errors
+    /// If the length cannot be taken, it returns the errno of the host.
+    pub fn size(&self) -> Result<usize, Error> {
+        let args = [self.handle as usize];
+        match unsafe { syscall(SYS_FLEN, args.as_ptr() as usize) } {
+            len if len < 0 => Err(last_error()),
+            len => Ok(len as usize),
+        }
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let args = [self.handle as usize];
+        unsafe { syscall(SYS_CLOSE, args.as_ptr() as usize) };
+    }
+}
+
+/// This function removes the file of the host.
+///
+/// # Errors
+/// If the file cannot be removed, it returns the errno of the host.
+pub fn remove(path: &CStr) -> Result<(), Error> {
+    let args = [path.as_ptr() as usize, path.count_bytes()];
+    match unsafe { syscall(SYS_REMOVE, args.as_ptr() as usize) } {
+        0 => Ok(()),
+        _ => Err(last_error()),
+    }
+}
@@ -0,0 +1,267 @@
+use core::{fmt, str::FromStr};
+
+use crate::{console, print, println};
+
+/// The maximum number of arguments including the command name.
+pub const MAX_ARGS: usize = 16;
+/// The maximum length of a command line in bytes.
+pub const LINE_LEN: usize = 128;
+
+//-------------------------------------------------------------------------------------------------
+// CommandError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the errors of commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    /// The argument of the index is not given.
+    MissingArgument(usize),
+    /// The argument of the index cannot be parsed.
+    InvalidArgument(usize),
+    /// The command line has more than `MAX_ARGS` arguments.
+    TooManyArguments,
+    /// A quote is not closed.
+    UnclosedQuote,
+    /// The command failed with the message.
+    Failed(&'static str),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::MissingArgument(i) => {
+                write!(f, "argument {} is missing", i)
+            }
+            CommandError::InvalidArgument(i) => {
+                write!(f, "argument {} is invalid", i)
+            }
+            CommandError::TooManyArguments => {
+                write!(f, "more than {} arguments", MAX_ARGS - 1)
+            }
+            CommandError::UnclosedQuote => write!(f, "a quote is not closed"),
+            CommandError::Failed(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl core::error::Error for CommandError {}
+
+//-------------------------------------------------------------------------------------------------
+// Args
+//-------------------------------------------------------------------------------------------------
+
+/// The arguments of a command line.
+/// The index 0 is the command name, and the arguments start at 1.
+///
+/// Arguments are separated by spaces,
+/// and a double-quoted argument can contain spaces.
+#[derive(Debug)]
+pub struct Args<'a> {
+    argv: [&'a str; MAX_ARGS],
+    argc: usize,
+}
+
+impl<'a> Args<'a> {
+    /// This function splits the command line into arguments.
+    ///
+    /// # Errors
+    /// If the line has more than `MAX_ARGS` arguments,
+    /// it returns `CommandError::TooManyArguments`,
+    /// and if a quote is not closed, `CommandError::UnclosedQuote`.
+    pub fn parse(line: &'a str) -> Result<Self, CommandError> {
+        let mut args = Args {
+            argv: [""; MAX_ARGS],
+            argc: 0,
+        };
+        let mut rest = line.trim_start();
+        while !rest.is_empty() {
+            let (arg, next) = if let Some(quoted) = rest.strip_prefix('"') {
+                let end =
+                    quoted.find('"').ok_or(CommandError::UnclosedQuote)?;
+                (&quoted[..end], &quoted[end + 1..])
+            } else {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            };
+            if args.argc == MAX_ARGS {
+                return Err(CommandError::TooManyArguments);
+            }
+            args.argv[args.argc] = arg;
+            args.argc += 1;
+            rest = next.trim_start();
+        }
+        Ok(args)
+    }
+
+    /// This function takes the command name.
+    pub fn name(&self) -> &'a str {
+        self.argv[0]
+    }
+
+    /// This function takes the number of arguments without the name.
+    pub fn len(&self) -> usize {
+        self.argc.saturating_sub(1)
+    }
+
+    /// This function checks whether no arguments are given.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This function takes the argument of the index, which starts at 1.
+    pub fn get(&self, index: usize) -> Option<&'a str> {
+        if 0 < index && index < self.argc {
+            Some(self.argv[index])
+        } else {
+            None
+        }
+    }
+
+    /// This function iterates the arguments without the name.
+    pub fn iter(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.argv[1.min(self.argc)..self.argc].iter().copied()
+    }
+
+    /// This function parses the argument of the index.
+    ///
+    /// # Errors
+    /// If the argument is not given, it returns `CommandError::MissingArgument`,
+    /// and if it cannot be parsed, `CommandError::InvalidArgument`.
+    pub fn parse_arg<T: FromStr>(
+        &self,
+        index: usize,
+    ) -> Result<T, CommandError> {
+        self.get(index)
+            .ok_or(CommandError::MissingArgument(index))?
+            .parse()
+            .map_err(|_| CommandError::InvalidArgument(index))
+    }
+
+    /// This function parses the argument of the index as an unsigned integer,
+    /// which can have the prefix `0x` or `0b`.
+    ///
+    /// # Errors
+    /// If the argument is not given, it returns `CommandError::MissingArgument`,
+    /// and if it cannot be parsed, `CommandError::InvalidArgument`.
+    pub fn parse_uint(&self, index: usize) -> Result<u64, CommandError> {
+        let arg = self
+            .get(index)
+            .ok_or(CommandError::MissingArgument(index))?;
+        let (digits, radix) = if let Some(hex) =
+            arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X"))
+        {
+            (hex, 16)
+        } else if let Some(bin) = arg.strip_prefix("0b") {
+            (bin, 2)
+        } else {
+            (arg, 10)
+        };
+        u64::from_str_radix(digits, radix)
+            .map_err(|_| CommandError::InvalidArgument(index))
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Shell
+//-------------------------------------------------------------------------------------------------
+
+/// A command of the shell.
+#[derive(Debug, Copy, Clone)]
+pub struct Command {
+    pub name: &'static str,
+    /// The usage and the description, which `help` prints.
+    pub help: &'static str,
+    pub run: fn(&Args) -> Result<(), CommandError>,
+}
+
+/// A command shell on the console.
+/// It has the built-in command `help`, which lists the commands.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{
+///     println,
+///     shell::{Args, Command, CommandError, Shell},
+/// };
+///
+/// fn read_word(args: &Args) -> Result<(), CommandError> {
+///     let addr = args.parse_uint(1)? as usize;
+///     let value = unsafe { core::ptr::read_volatile(addr as *const u32) };
+///     println!("0x{:08x}: 0x{:08x}", addr, value);
+///     Ok(())
+/// }
+///
+/// fn write_word(args: &Args) -> Result<(), CommandError> {
+///     let addr = args.parse_uint(1)? as usize;
+///     let value = args.parse_uint(2)? as u32;
+///     unsafe { core::ptr::write_volatile(addr as *mut u32, value) };
+///     Ok(())
+/// }
+///
+/// const COMMANDS: &[Command] = &[
+///     Command { name: "md", help: "md <addr>  read a word", run: read_word },
+///     Command { name: "mw", help: "mw <addr> <value>  write a word", run: write_word },
+/// ];
+///
+/// Shell::new("r5> ", COMMANDS).run();
+/// ```
+pub struct Shell<'a> {
+    prompt: &'a str,
+    commands: &'a [Command],
+}
+
+impl<'a> Shell<'a> {
+    /// This function creates a shell with the prompt and the commands.
+    pub const fn new(prompt: &'a str, commands: &'a [Command]) -> Self {
+        Self { prompt, commands }
+    }
+
+    /// This function runs the command line, and prints the error if any.
+    pub fn execute(&self, line: &str) {
+        if let Err(e) = self.try_execute(line) {
+            println!("error: {}", e);
+        }
+    }
+
+    /// This function runs the command line.
+    /// An empty line does nothing.
+    ///
+    /// # Errors
+    /// If the command is not found, it returns `CommandError::Failed`,
+    /// and otherwise the error of the command.
+    pub fn try_execute(&self, line: &str) -> Result<(), CommandError> {
+        let args = Args::parse(line)?;
+        if args.argc == 0 {
+            return Ok(());
+        }
+        if args.name() == "help" {
+            self.help();
+            return Ok(());
+        }
+        match self.commands.iter().find(|c| c.name == args.name()) {
+            Some(command) => (command.run)(&args),
+            None => Err(CommandError::Failed("unknown command, try help")),
+        }
+    }
+
+    /// This function prints the help of the commands.
+    pub fn help(&self) {
+        println!("help  list the commands");
+        for command in self.commands {
+            println!("{}", command.help);
+        }
+    }
+
+    /// This function reads and runs command lines forever.
+    pub fn run(&self) -> ! {
+        let stdin = console::stdin();
+        let mut buf = [0; LINE_LEN];
+        loop {
+            print!("{}", self.prompt);
+            match stdin.read_line(&mut buf) {
+                Ok(line) => self.execute(line),
+                Err(e) => println!("error: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+extern crate embeddedsw_sys;
+use core::{
+    arch::asm,
+    ffi, fmt,
+    ptr::{self, addr_of},
+    sync::atomic::{AtomicU32, Ordering},
+};
+use embeddedsw_sys as esys;
+
+use crate::{
+    mpu::{self, MpuError, RegionAttributes},
+    println,
+    xscugic::interrupt_free,
+};
+
+/// The size of the stack which the data abort handler switches to.
+/// The abort stack is too small for the panic handler.
+const FAULT_STACK_SIZE: usize = 4096;
+
+/// The pattern written into unused stacks.
+const PAINT: u32 = 0xdead_beef;
+
+/// The part of the current stack just below the stack pointer is not painted,
+/// because the painting function itself uses it.
+const PAINT_MARGIN: usize = 256;
+
+/// The recommended size of a guard region.
+/// A function whose frame is larger than the guard can skip over it,
+/// so the guard should be larger than the largest frame.
+pub const GUARD_SIZE: usize = 512;
+
+// Symbols defined by the linker script (lscript.ld) of the Xilinx BSP.
+// Each stack grows down from `__X_stack` to `_X_stack_end`.
+extern "C" {
+    static _stack_end: u8;
+    static __stack: u8;
+    static _irq_stack_end: u8;
+    static __irq_stack: u8;
+    static _supervisor_stack_end: u8;
+    static __supervisor_stack: u8;
+    static _abort_stack_end: u8;
+    static __abort_stack: u8;
+    static _fiq_stack_end: u8;
+    static __fiq_stack: u8;
+    static _undef_stack_end: u8;
+    static __undef_stack: u8;
+}
+
+extern "C" {
+    /// The address of the instruction which caused the data abort,
+    /// stored by the data abort vector of the Xilinx BSP.
+    static DataAbortAddr: u32;
+}
+
+//-------------------------------------------------------------------------------------------------
+// StackKind
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the stacks of processor modes.
+#[repr(usize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StackKind {
+    /// SYS and USR mode, which runs main.
+    Sys,
+    Irq,
+    Fiq,
+    Abort,
+    Undef,
+    Supervisor,
+}
+
+impl StackKind {
+    /// All stacks.
+    pub const ALL: [StackKind; 6] = [
+        StackKind::Sys,
+        StackKind::Irq,
+        StackKind::Fiq,
+        StackKind::Abort,
+        StackKind::Undef,
+        StackKind::Supervisor,
+    ];
+
+    /// This function takes the stack defined by the linker script.
+    pub fn stack(self) -> Stack {
+        // taking the addresses of the symbols does not read them.
+        let (bottom, top) = match self {
+            StackKind::Sys => (addr_of!(_stack_end), addr_of!(__stack)),
+            StackKind::Irq => (addr_of!(_irq_stack_end), addr_of!(__irq_stack)),
+            StackKind::Fiq => (addr_of!(_fiq_stack_end), addr_of!(__fiq_stack)),
+            StackKind::Abort => {
+                (addr_of!(_abort_stack_end), addr_of!(__abort_stack))
+            }
+            StackKind::Undef => {
+                (addr_of!(_undef_stack_end), addr_of!(__undef_stack))
+            }
+            StackKind::Supervisor => (
+                addr_of!(_supervisor_stack_end),
+                addr_of!(__supervisor_stack),
+            ),
+        };
+        Stack {
+            kind: self,
+            bottom: bottom as usize,
+            top: top as usize,
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Stack
+//-------------------------------------------------------------------------------------------------
+
+/// The stack of a processor mode.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Stack {
+    pub kind: StackKind,
+    /// The lowest address, which the stack reaches when it is full.
+    pub bottom: usize,
+    /// The initial stack pointer.
+    pub top: usize,
+}
+
+impl Stack {
+    /// This function takes the size in bytes.
+    pub fn size(&self) -> usize {
+        self.top - self.bottom
+    }
+
+    /// This function checks whether the address is in the stack.
+    pub fn contains(&self, addr: usize) -> bool {
+        self.bottom <= addr && addr < self.top
+    }
+
+    /// This function takes the lowest address which the stack can use,
+    /// that is, the end of the guard region if it is installed.
+    /// Any access to the guard faults, so it is neither painted nor scanned.
+    fn usable_bottom(&self) -> usize {
+        let guard = &GUARDS[self.kind as usize];
+        let base = guard.base.load(Ordering::Acquire) as usize;
+        let size = guard.size.load(Ordering::Acquire) as usize;
+        let bottom = if base == 0 { self.bottom } else { base + size };
+        (bottom.max(self.bottom) + 3) & !3
+    }
+
+    /// This function paints the unused part of the stack.
+    ///
+    /// # Safety
+    /// The stack must not be used by other contexts,
+    /// that is, the mode of the stack must not be entered while painting.
+    unsafe fn paint(&self, sp: usize) {
+        let end = if self.contains(sp) {
+            sp.saturating_sub(PAINT_MARGIN).max(self.bottom)
+        } else {
+            self.top
+        };
+        let mut addr = self.usable_bottom();
+        while addr + 4 <= end {
+            ptr::write_volatile(addr as *mut u32, PAINT);
+            addr += 4;
+        }
+    }
+
+    /// This function takes the maximum usage in bytes since painting.
+    /// The stack is scanned from the bottom until a word is not painted.
+    pub fn high_water_mark(&self) -> usize {
+        let mut addr = self.usable_bottom();
+        while addr + 4 <= self.top
+            && unsafe { ptr::read_volatile(addr as *const u32) } == PAINT
+        {
+            addr += 4;
+        }
+        self.top - addr
+    }
+}
+
+#[inline(always)]
+fn current_sp() -> usize {
+    let sp: usize;
+    unsafe { asm!("mov {}, sp", out(reg) sp, options(nomem, nostack)) };
+    sp
+}
+
+//-------------------------------------------------------------------------------------------------
+// Painting and reports
+//-------------------------------------------------------------------------------------------------
+
+/// This function paints all stacks, so `high_water_mark` can measure them.
+///
+/// Call it at the beginning of main, before enabling interrupts.
+/// The part of the current stack in use is not painted.
+///
+/// # Safety
+/// No other mode may use its stack while painting,
+/// so do not call it from exception handlers.
+pub unsafe fn paint() {
+    interrupt_free(|| {
+        let sp = current_sp();
+        for kind in StackKind::ALL {
+            kind.stack().paint(sp);
+        }
+    })
+}
+
+/// The usage of a stack.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct StackUsage {
+    pub kind: StackKind,
+    pub size: usize,
+    /// The maximum usage in bytes since painting.
+    pub used: usize,
+}
+
+/// This function takes the usage of the stack.
+pub fn usage(kind: StackKind) -> StackUsage {
+    let stack = kind.stack();
+    StackUsage {
+        kind,
+        size: stack.size(),
+        used: stack.high_water_mark(),
+    }
+}
+
+/// This function prints the usage of all stacks to the UART.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::stack;
+///
+/// unsafe { stack::paint() };
+/// // run the application
+/// stack::report();
+/// ```
+pub fn report() {
+    println!("stack      used /  size");
+    for kind in StackKind::ALL {
+        let usage = usage(kind);
+        println!(
+            "{:10} {:5} / {:5} ({}%)",
+            kind_name(kind),
+            usage.used,
+            usage.size,
+            if usage.size == 0 {
+                0
+            } else {
+                usage.used * 100 / usage.size
+            },
+        );
+    }
+}
+
+fn kind_name(kind: StackKind) -> &'static str {
+    match kind {
+        StackKind::Sys => "SYS",
+        StackKind::Irq => "IRQ",
+        StackKind::Fiq => "FIQ",
+        StackKind::Abort => "ABT",
+        StackKind::Undef => "UND",
+        StackKind::Supervisor => "SVC",
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Guard regions
+//-------------------------------------------------------------------------------------------------
+
+/// The guard region of a stack.
+struct Guard {
+    /// The base address (0 means none).
+    base: AtomicU32,
+    size: AtomicU32,
+}
+
+static GUARDS: [Guard; 6] = [const {
+    Guard {
+        base: AtomicU32::new(0),
+        size: AtomicU32::new(0),
+    }
+}; 6];
+
+/// This function protects the bottom of the stack with a MPU region,
+/// which faults on any access.
+/// It also installs the data abort handler,
+/// which panics when the stack overflows into the guard,
+/// so the policy of the panic handler applies to the overflow.
+///
+/// The guard takes `size` bytes from the bottom of the stack,
+/// which are not painted nor scanned by `high_water_mark` any more.
+/// `GUARD_SIZE` is recommended for the size.
+/// The region number must be higher than the regions covering the stack,
+/// because higher regions take priority.
+///
+/// # Errors
+/// - If the size is not a power of two, it returns `InvalidSize`.
+/// - If the stack cannot hold the guard, it returns `InvalidSize`.
+/// - If the region cannot be configured, it returns the MPU error.
+///
+/// # Safety
+/// The guard region must not be in use,
+/// and the region number must not be used by other configurations.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::stack::{self, StackKind};
+///
+/// unsafe { stack::install_guard(StackKind::Sys, 15, stack::GUARD_SIZE) }
+///     .unwrap();
+/// ```
+pub unsafe fn install_guard(
+    kind: StackKind,
+    region: u32,
+    size: usize,
+) -> Result<(), MpuError> {
+    if !size.is_power_of_two() {
+        return Err(MpuError::InvalidSize);
+    }
+    let stack = kind.stack();
+    let base = (stack.bottom + size - 1) & !(size - 1);
+    if base + size > stack.top {
+        return Err(MpuError::InvalidSize);
+    }
+
+    mpu::set_region(
+        region,
+        base as u32,
+        size as u64,
+        RegionAttributes::NO_ACCESS,
+    )?;
+    let guard = &GUARDS[kind as usize];
+    guard.size.store(size as u32, Ordering::Release);
+    guard.base.store(base as u32, Ordering::Release);
+
+    let handler = data_abort_handler as unsafe extern "C" fn(*mut ffi::c_void);
+    esys::Xil_ExceptionRegisterHandler(
+        esys::XIL_EXCEPTION_ID_DATA_ABORT_INT,
+        Some(handler),
+        ptr::null_mut(),
+    );
+    Ok(())
+}
+
+/// This function takes the stack whose guard contains the address.
+fn guard_of(addr: usize) -> Option<StackKind> {
+    StackKind::ALL.into_iter().find(|kind| {
+        let guard = &GUARDS[*kind as usize];
+        let base = guard.base.load(Ordering::Acquire) as usize;
+        let size = guard.size.load(Ordering::Acquire) as usize;
+        base != 0 && base <= addr && addr < base + size
+    })
+}
+
+/// The stack of `fault_panic`.
+#[repr(C, align(8))]
+struct FaultStack([u8; FAULT_STACK_SIZE]);
+
+static mut FAULT_STACK: FaultStack = FaultStack([0; FAULT_STACK_SIZE]);
+
+/// The data abort handler, which runs on the abort stack.
+/// The abort stack is too small for the panic handler,
+/// so it switches to `FAULT_STACK` and panics there.
+/// The interrupted code never resumes, so the abort stack is not restored.
+unsafe extern "C" fn data_abort_handler(_data: *mut ffi::c_void) {
+    let dfar: u32;
+    let dfsr: u32;
+    asm!("mrc p15, 0, {}, c6, c0, 0", out(reg) dfar, options(nomem, nostack));
+    asm!("mrc p15, 0, {}, c5, c0, 0", out(reg) dfsr, options(nomem, nostack));
+    let lr = ptr::read_volatile(addr_of!(DataAbortAddr));
+    let sp = faulting_sp();
+
+    let top = addr_of!(FAULT_STACK) as usize + FAULT_STACK_SIZE;
+    let entry = fault_panic as extern "C" fn(u32, u32, u32, u32) -> !;
+    asm!(
+        "mov sp, {top}",
+        "bx {entry}",
+        top = in(reg) top,
+        entry = in(reg) entry,
+        in("r0") dfar,
+        in("r1") dfsr,
+        in("r2") lr,
+        in("r3") sp,
+        options(noreturn),
+    );
+}
+
+/// This function reads the SP of the mode which took the data abort.
+/// USR mode shares the SP with SYS mode, so SYS mode is used instead,
+/// which can switch back to ABT mode.
+unsafe fn faulting_sp() -> u32 {
+    let sp: u32;
+    asm!(
+        "mrs {mode}, spsr",
+        "and {mode}, {mode}, #0x1f",
+        "cmp {mode}, #0x10",
+        "moveq {mode}, #0x1f",
+        "orr {mode}, {mode}, #0xc0",
+        "mrs {cpsr}, cpsr",
+        "msr cpsr_c, {mode}",
+        "mov {sp}, sp",
+        "msr cpsr_c, {cpsr}",
+        mode = out(reg) _,
+        cpsr = out(reg) _,
+        sp = out(reg) sp,
+        options(nomem, nostack),
+    );
+    sp
+}
+
+/// This function panics with the data abort, which runs on `FAULT_STACK`.
+/// With the `crash-record` feature, the fault is recorded first.
+extern "C" fn fault_panic(dfar: u32, dfsr: u32, lr: u32, sp: u32) -> ! {
+    match guard_of(dfar as usize) {
+        Some(kind) => fault(
+            lr,
+            sp,
+            format_args!(
+                "stack overflow: the {} stack overflowed \
+                 (fault address 0x{:08x})",
+                kind_name(kind),
+                dfar
+            ),
+        ),
+        None => fault(
+            lr,
+            sp,
+            format_args!(
+                "data abort: fault address 0x{:08x}, DFSR 0x{:08x}",
+                dfar, dfsr
+            ),
+        ),
+    }
+}
+
+#[cfg_attr(not(feature = "crash-record"), allow(unused_variables))]
+fn fault(lr: u32, sp: u32, args: fmt::Arguments) -> ! {
+    #[cfg(feature = "crash-record")]
+    crate::crash::record_fault(lr, sp, args);
+    panic!("{}", args)
+}
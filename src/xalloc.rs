@@ -1,16 +1,36 @@
 extern crate embeddedsw_sys;
 use core::alloc::{self, GlobalAlloc};
 use core::ffi::c_void;
+use core::ptr;
 use embeddedsw_sys as esys;
 
+/// The alignment which malloc of newlib guarantees.
+const MIN_ALIGN: usize = 8;
+
+#[cfg(feature = "xalloc-global")]
 #[alloc_error_handler]
 fn alloc_error_handler(layout: alloc::Layout) -> ! {
     panic!("allocation error: {:?}", layout);
 }
 
+#[cfg(feature = "xalloc-global")]
 #[global_allocator]
 static ALLOCATOR: XAllocator = XAllocator::new();
 
+/// The allocator using Xilinx's malloc (newlib).
+///
+/// The `xalloc-global` feature installs it as the global allocator.
+/// With only the `xalloc` feature, it is not installed,
+/// and the application can install it by itself,
+/// or install another global allocator.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::xalloc::XAllocator;
+///
+/// #[global_allocator]
+/// static ALLOCATOR: XAllocator = XAllocator::new();
+/// ```
 pub struct XAllocator {}
 
 impl XAllocator {
@@ -21,10 +41,51 @@ impl XAllocator {
 
 unsafe impl GlobalAlloc for XAllocator {
     unsafe fn alloc(&self, layout: alloc::Layout) -> *mut u8 {
-        esys::malloc(layout.size() as u32) as *mut _
+        if layout.align() <= MIN_ALIGN {
+            esys::malloc(layout.size() as _) as *mut _
+        } else {
+            esys::memalign(layout.align() as _, layout.size() as _) as *mut _
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: alloc::Layout) -> *mut u8 {
+        if layout.align() <= MIN_ALIGN {
+            esys::calloc(1, layout.size() as _) as *mut _
+        } else {
+            let ptr = self.alloc(layout);
+            if !ptr.is_null() {
+                ptr::write_bytes(ptr, 0, layout.size());
+            }
+            ptr
+        }
     }
 
     unsafe fn dealloc(&self, ptr: *mut u8, _layout: alloc::Layout) {
         esys::free(ptr as *mut c_void)
     }
+
+    unsafe fn realloc(
+        &self,
+        ptr: *mut u8,
+        layout: alloc::Layout,
+        new_size: usize,
+    ) -> *mut u8 {
+        if layout.align() <= MIN_ALIGN {
+            return esys::realloc(ptr as *mut c_void, new_size as _) as *mut _;
+        }
+
+        // realloc of newlib does not keep the alignment of memalign.
+        let new_layout =
+            alloc::Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(
+                ptr,
+                new_ptr,
+                core::cmp::min(layout.size(), new_size),
+            );
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
 }
@@ -1,20 +1,58 @@
+extern crate alloc;
 extern crate embeddedsw_sys;
-use core::mem::MaybeUninit;
+use alloc::{alloc::Global, boxed::Box};
+use core::alloc::Allocator;
+use core::marker::PhantomData;
+use core::mem::{size_of, MaybeUninit};
+use core::ops::{Deref, DerefMut};
 
 use embeddedsw_sys as esys;
 
+use crate::cache::{self, CacheRange};
+use crate::xstatus::XStatus;
+
 /// Enumurates for DMA driver errors
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DmaError {
     InvalidParam,
     NotInit,
     NotSGDMA,
-    Unknown,
+    /// The driver returned the other XST_* status code.
+    Unknown(XStatus),
     Channel,
     Submisson,
     ConfigInit,
 }
 
+impl DmaError {
+    /// This function takes the XST_* status code of the error.
+    pub fn status(&self) -> XStatus {
+        match self {
+            DmaError::InvalidParam => XStatus::InvalidParam,
+            DmaError::NotInit => XStatus::DeviceIsStopped,
+            DmaError::NotSGDMA => XStatus::NotSgdma,
+            DmaError::Unknown(status) => *status,
+            DmaError::Channel => XStatus::DmaError,
+            DmaError::Submisson => XStatus::Failure,
+            DmaError::ConfigInit => XStatus::DeviceNotFound,
+        }
+    }
+}
+
+impl core::fmt::Display for DmaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:?}: {}", self, self.status())
+    }
+}
+
+impl core::error::Error for DmaError {}
+
+impl From<DmaError> for XStatus {
+    fn from(e: DmaError) -> XStatus {
+        e.status()
+    }
+}
+
 /// Enumerates for DMA directions
 ///
 /// DMAToDevice: PS  -> DDR
@@ -33,7 +71,7 @@ pub enum DmaError {
 ///                 +------+
 ///                 |  PL  |
 ///                 +------+
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum DmaDirection {
     DMAToDevice,
     DeviceToDMA,
@@ -111,7 +149,7 @@ impl XAxiDmaConfig {
 /// # extern crate alloc;
 /// extern crate embeddedsw_rs;
 /// use embeddedsw_rs as xemb;
-/// use xemb::{ println, xaxidma::{ self, XAxiDma, XAxiDmaXonfig }, raw::* };
+/// use xemb::{ println, cache::{ self, CacheRange }, peripherals::Peripherals, xaxidma::{ self, DmaDirection::* }, raw };
 /// # [panic_handler]
 /// # fn panic(info: &core::panic::PanicInfo<'_>) -> ! {
 /// #    println!("{}", info);
@@ -123,19 +161,16 @@ impl XAxiDmaConfig {
 /// #[start]
 /// fn main(_argc: isize, _argv: *const *const u8) -> isize {
 ///    // XPAR_AXI_DMA_0_DEVICE_ID is defined in parameters.h, so bindgen generates constants.
-///    let mut axi_dma_config = XAxiDmaConfig::lookup_config(raw::XPAR_AXI_DMA_0_DEVICE_ID).unwarap();
-///    
-///    // initalize DMA instance
-///    let mut axi_dma = MaybeUninit::<XAxiDma>::uninit();
-///    XAxiDma::cfg_initialize(&mut axi_dma, &mut axi_dma_config).unwrap();
-///    let mut axi_dma = axi_dma.assume_init();
+///    // take the initialized DMA instance
+///    let mut p = Peripherals::take().unwrap();
+///    let axi_dma = p.axidma(raw::XPAR_AXI_DMA_0_DEVICE_ID).unwrap();
 ///    
 ///    // disable IRQ interrupt
 ///    axi_dma.axi_irq_interrupt_disable(DMAToDevice);
 ///    axi_dma.axi_irq_interrupt_disable(DeviceToDMA);
 ///
 ///    // flush DCache
-///    xaxidma::xil_dcach_flush_range(dma_base_addr as isize, dma_size);
+///    cache::dcache_flush_range(CacheRange::new(dma_base_addr, dma_size));
 ///    
 ///    // simple DMA transfer
 ///    axi_dma.axi_simple_transfer(dma_base_addr, dma_size, xaxidma::DMAToDevice).unwrap();
@@ -154,10 +189,8 @@ pub struct XAxiDma {
 
 impl XAxiDma {
     /// This function inilializes a DMA instance.
-    ///
-    /// # Safety  
-    /// This function must be called before using DMA instances.
-    pub fn cfg_initialize(
+    /// Take an initialized instance with `Peripherals::axidma`.
+    pub(crate) fn cfg_initialize(
         xaxidma: &mut MaybeUninit<XAxiDma>,
         config: &mut XAxiDmaConfig,
     ) -> Result<(), DmaError> {
@@ -170,7 +203,7 @@ impl XAxiDma {
         {
             esys::XST_SUCCESS => Ok(()),
             esys::XST_INVALID_PARAM => Err(DmaError::InvalidParam),
-            _ => Err(DmaError::Unknown),
+            status => Err(DmaError::Unknown(XStatus::from(status))),
         }
     }
 
@@ -196,13 +229,13 @@ impl XAxiDma {
     /// - If this function is called before initalizing XAxiDMA structs,
     ///   it returns DmaError variants for NotSGDMA.
     /// - If this function cannot pause AXI DMA transactions due to unknown error,
-    ///   it returns DMAError variants for Unknown.
+    ///   it returns DMAError variants for Unknown with the status code.
     pub fn pause(&mut self) -> Result<(), DmaError> {
         unsafe {
             match esys::XAxiDma_Pause(&mut self.inner as *mut _) as u32 {
                 esys::XST_SUCCESS => Ok(()),
                 esys::XST_NOT_SGDMA => Err(DmaError::NotSGDMA),
-                _ => Err(DmaError::Unknown),
+                status => Err(DmaError::Unknown(XStatus::from(status))),
             }
         }
     }
@@ -213,14 +246,14 @@ impl XAxiDma {
     /// - If this function is called before initalizing XAxiDMA structs,
     ///   it returns DmaError variants for NotSGDMA.
     /// - If this function cannot pause AXI DMA transactions due to unknown error,
-    ///   it returns DMAError variants for Unknown.
+    ///   it returns DMAError variants for Unknown with the status code.
     pub fn resume(&mut self) -> Result<(), DmaError> {
         unsafe {
             match esys::XAxiDma_Resume(&mut self.inner as *mut _) as u32 {
                 esys::XST_SUCCESS => Ok(()),
                 esys::XST_NOT_SGDMA => Err(DmaError::NotSGDMA),
                 esys::XST_DMA_ERROR => Err(DmaError::Channel),
-                _ => Err(DmaError::Unknown),
+                status => Err(DmaError::Unknown(XStatus::from(status))),
             }
         }
     }
@@ -269,7 +302,7 @@ impl XAxiDma {
                 esys::XST_SUCCESS => Ok(()),
                 esys::XST_FAILURE => Err(DmaError::Submisson),
                 esys::XST_INVALID_PARAM => Err(DmaError::InvalidParam),
-                _ => Err(DmaError::Unknown),
+                status => Err(DmaError::Unknown(XStatus::from(status))),
             }
         }
     }
@@ -279,7 +312,7 @@ impl XAxiDma {
         unsafe {
             match esys::XAxiDma_Selftest(&mut self.inner as *mut _) as u32 {
                 esys::XST_SUCCESS => Ok(()),
-                _ => Err(DmaError::Unknown),
+                status => Err(DmaError::Unknown(XStatus::from(status))),
             }
         }
     }
@@ -313,6 +346,24 @@ impl XAxiDma {
         }
     }
 
+    /// This function acknowledges the pending IRQ interrupts of the direction.
+    /// It returns the acknowledged interrupt bits,
+    /// so call it at the beginning of the DMA interrupt handler.
+    pub fn irq_ack(&self, direction: DmaDirection) -> u32 {
+        let direction = match direction {
+            DmaDirection::DMAToDevice => esys::XAXIDMA_DMA_TO_DEVICE,
+            DmaDirection::DeviceToDMA => esys::XAXIDMA_DEVICE_TO_DMA,
+        };
+        unsafe {
+            let base_addr = self.inner.RegBase as u32
+                + (esys::XAXIDMA_RX_OFFSET * direction);
+            let pending = Self::read_reg(base_addr, esys::XAXIDMA_SR_OFFSET)
+                & esys::XAXIDMA_IRQ_ALL_MASK;
+            Self::write_reg(base_addr, esys::XAXIDMA_SR_OFFSET, pending);
+            pending
+        }
+    }
+
     /// This function disables IRQ interrupt.
     pub fn irq_interrupt_disable(&self, direction: DmaDirection) {
         let direction = match direction {
@@ -335,11 +386,330 @@ impl XAxiDma {
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// DmaBuffer
+//-------------------------------------------------------------------------------------------------
+
+pub use crate::cache::CACHE_LINE_SIZE;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Plain data which is valid for any bit pattern,
+/// so the device can write it directly.
+///
+/// It is implemented for integer types and arrays of them,
+/// and it cannot be implemented outside of this crate.
+pub trait DmaData: sealed::Sealed {}
+
+macro_rules! impl_dma_data {
+    ($($t:ty),*) => {
+        $(
+            impl sealed::Sealed for $t {}
+            impl DmaData for $t {}
+        )*
+    };
+}
+
+impl_dma_data!(u8, u16, u32, u64, u128, usize);
+impl_dma_data!(i8, i16, i32, i64, i128, isize);
+
+impl<T: DmaData, const N: usize> sealed::Sealed for [T; N] {}
+impl<T: DmaData, const N: usize> DmaData for [T; N] {}
+
+/// The data aligned and padded to cache lines,
+/// so no other data shares a cache line with it.
+#[repr(C, align(32))]
+struct CacheAligned<T>(T);
+
+/// A buffer which DMA transfers can own.
+///
+/// The buffer is aligned and padded to cache lines,
+/// and it is allocated by the allocator `A`, such as `region::DDR`.
+/// `T` is plain data such as an array of integers (see `DmaData`),
+/// because the device writes it directly.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{region::DDR, xaxidma::DmaBuffer};
+///
+/// let mut tx = DmaBuffer::new_in([0u32; 256], &DDR);
+/// tx[0] = 0xdead_beef;
+/// let rx = DmaBuffer::new_in([0u32; 256], &DDR);
+///
+/// let (mut mm2s, mut s2mm) = axi_dma.split();
+/// // both channels are in flight at the same time (e.g. a loopback).
+/// let rx = s2mm.transfer(rx).map_err(|(e, _)| e)?;
+/// let tx = mm2s.transfer(tx).map_err(|(e, _)| e)?;
+/// let tx = tx.wait();
+/// let rx = rx.wait();
+/// println!("{:x}", rx[0]);
+/// ```
+pub struct DmaBuffer<T: DmaData, A: Allocator = Global> {
+    inner: Box<CacheAligned<T>, A>,
+}
+
+impl<T: DmaData> DmaBuffer<T> {
+    /// This function allocates a buffer by the global allocator.
+    pub fn new(value: T) -> Self {
+        Self::new_in(value, Global)
+    }
+}
+
+impl<T: DmaData, A: Allocator> DmaBuffer<T, A> {
+    /// This function allocates a buffer by the allocator.
+    pub fn new_in(value: T, alloc: A) -> Self {
+        Self {
+            inner: Box::new_in(CacheAligned(value), alloc),
+        }
+    }
+
+    /// This function takes the address of the buffer.
+    pub fn addr(&self) -> usize {
+        &self.inner.0 as *const T as usize
+    }
+
+    /// This function takes the size of the buffer in bytes.
+    pub fn len(&self) -> usize {
+        size_of::<T>()
+    }
+
+    /// This function checks whether the buffer has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This function writes the cache lines of the buffer back to memory,
+    /// so the device reads the data which the CPU wrote.
+    fn flush(&self) {
+        unsafe {
+            esys::Xil_DCacheFlushRange(
+                self.addr() as _,
+                size_of::<CacheAligned<T>>() as u32,
+            )
+        }
+    }
+
+    /// This function discards the cache lines of the buffer,
+    /// so the CPU reads the data which the device wrote.
+    /// The buffer does not share cache lines with other data,
+    /// so no other data is discarded.
+    fn invalidate(&self) {
+        unsafe {
+            esys::Xil_DCacheInvalidateRange(
+                self.addr() as _,
+                size_of::<CacheAligned<T>>() as u32,
+            )
+        }
+    }
+}
+
+impl<T: DmaData, A: Allocator> Deref for DmaBuffer<T, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.0
+    }
+}
+
+impl<T: DmaData, A: Allocator> DerefMut for DmaBuffer<T, A> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner.0
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// DmaTransfer
+//-------------------------------------------------------------------------------------------------
+
+/// A simple transfer in progress, which owns the buffer.
+///
+/// The buffer is returned by `wait` after the device releases it.
+/// If the transfer is dropped, it waits for the device,
+/// so the memory is never freed while the device accesses it.
+pub struct DmaTransfer<'a, T: DmaData, A: Allocator = Global> {
+    channel: &'a mut DmaChannel,
+    buffer: Option<DmaBuffer<T, A>>,
+}
+
+impl<'a, T: DmaData, A: Allocator> DmaTransfer<'a, T, A> {
+    /// This function checks whether the transfer completes.
+    pub fn is_done(&mut self) -> bool {
+        !self.channel.busy()
+    }
+
+    /// This function waits until the transfer completes,
+    /// and returns the buffer.
+    pub fn wait(mut self) -> DmaBuffer<T, A> {
+        self.finish();
+        self.buffer.take().unwrap()
+    }
+
+    fn finish(&mut self) {
+        while self.channel.busy() {}
+        if self.channel.direction == DmaDirection::DeviceToDMA {
+            // discard the lines which were speculatively loaded
+            // during the transfer.
+            if let Some(buffer) = &self.buffer {
+                buffer.invalidate();
+            }
+        }
+    }
+}
+
+impl<'a, T: DmaData, A: Allocator> Drop for DmaTransfer<'a, T, A> {
+    fn drop(&mut self) {
+        if self.buffer.is_some() {
+            self.finish();
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Mm2s and S2mm
+//-------------------------------------------------------------------------------------------------
+
+/// One channel of a DMA instance.
+///
+/// The driver functions of a channel only access the registers
+/// and the ring of its own direction,
+/// so both channels can be used at the same time.
+struct DmaChannel {
+    dma: *mut esys::XAxiDma,
+    direction: DmaDirection,
+}
+
+impl DmaChannel {
+    fn busy(&self) -> bool {
+        let direction = match self.direction {
+            DmaDirection::DMAToDevice => esys::XAXIDMA_DMA_TO_DEVICE,
+            DmaDirection::DeviceToDMA => esys::XAXIDMA_DEVICE_TO_DMA,
+        };
+        unsafe { esys::XAxiDma_Busy(self.dma, direction as i32) != 0 }
+    }
+
+    fn start_transfer<T: DmaData, A: Allocator>(
+        &mut self,
+        buffer: DmaBuffer<T, A>,
+    ) -> Result<DmaTransfer<'_, T, A>, (DmaError, DmaBuffer<T, A>)> {
+        let direction = match self.direction {
+            DmaDirection::DMAToDevice => esys::XAXIDMA_DMA_TO_DEVICE,
+            DmaDirection::DeviceToDMA => esys::XAXIDMA_DEVICE_TO_DMA,
+        };
+        let status = unsafe {
+            esys::XAxiDma_SimpleTransfer(
+                self.dma,
+                buffer.addr(),
+                buffer.len() as u32,
+                direction as i32,
+            )
+        };
+        let error = match status {
+            esys::XST_SUCCESS => {
+                return Ok(DmaTransfer {
+                    channel: self,
+                    buffer: Some(buffer),
+                })
+            }
+            esys::XST_FAILURE => DmaError::Submisson,
+            esys::XST_INVALID_PARAM => DmaError::InvalidParam,
+            status => DmaError::Unknown(XStatus::from(status)),
+        };
+        Err((error, buffer))
+    }
+}
+
+/// The MM2S channel (memory to device) of a DMA instance,
+/// which is taken by `XAxiDma::split`.
+pub struct Mm2s<'d> {
+    channel: DmaChannel,
+    _dma: PhantomData<&'d mut XAxiDma>,
+}
+
+impl<'d> Mm2s<'d> {
+    /// This function checks whether the channel is busy.
+    pub fn busy(&self) -> bool {
+        self.channel.busy()
+    }
+
+    /// This function flushes the buffer and starts
+    /// a simple transfer from the buffer to the device.
+    ///
+    /// # Errors
+    /// If this function cannot submit the transfer,
+    /// it returns the error and the buffer.
+    pub fn transfer<T: DmaData, A: Allocator>(
+        &mut self,
+        buffer: DmaBuffer<T, A>,
+    ) -> Result<DmaTransfer<'_, T, A>, (DmaError, DmaBuffer<T, A>)> {
+        buffer.flush();
+        self.channel.start_transfer(buffer)
+    }
+}
+
+/// The S2MM channel (device to memory) of a DMA instance,
+/// which is taken by `XAxiDma::split`.
+pub struct S2mm<'d> {
+    channel: DmaChannel,
+    _dma: PhantomData<&'d mut XAxiDma>,
+}
+
+impl<'d> S2mm<'d> {
+    /// This function checks whether the channel is busy.
+    pub fn busy(&self) -> bool {
+        self.channel.busy()
+    }
+
+    /// This function invalidates the buffer and starts
+    /// a simple transfer from the device to the buffer.
+    /// The buffer is invalidated again when the transfer completes.
+    ///
+    /// # Errors
+    /// If this function cannot submit the transfer,
+    /// it returns the error and the buffer.
+    pub fn transfer<T: DmaData, A: Allocator>(
+        &mut self,
+        buffer: DmaBuffer<T, A>,
+    ) -> Result<DmaTransfer<'_, T, A>, (DmaError, DmaBuffer<T, A>)> {
+        // dirty lines must not be written back over the received data.
+        buffer.invalidate();
+        self.channel.start_transfer(buffer)
+    }
+}
+
+impl XAxiDma {
+    /// This function splits the DMA instance into its MM2S and S2MM channels,
+    /// so a transfer of each direction can be in flight at the same time.
+    pub fn split(&mut self) -> (Mm2s<'_>, S2mm<'_>) {
+        let dma = &mut self.inner as *mut esys::XAxiDma;
+        (
+            Mm2s {
+                channel: DmaChannel {
+                    dma,
+                    direction: DmaDirection::DMAToDevice,
+                },
+                _dma: PhantomData,
+            },
+            S2mm {
+                channel: DmaChannel {
+                    dma,
+                    direction: DmaDirection::DeviceToDMA,
+                },
+                _dma: PhantomData,
+            },
+        )
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Public functions
 //-------------------------------------------------------------------------------------------------
 
 /// This function flishs DCache.
+#[deprecated(note = "use `cache::dcache_flush_range` or `DmaBuffer` instead")]
 pub fn xil_dcache_flush_range(addr: isize, length: u32) {
-    unsafe { esys::Xil_DCacheFlushRange(addr, length) }
+    cache::dcache_flush_range(
+        CacheRange::new(addr as usize, length as usize).expand_to_lines(),
+    )
 }
@@ -0,0 +1,157 @@
+extern crate embeddedsw_sys;
+use core::{
+    ffi::CStr,
+    ptr::{self, addr_of, addr_of_mut},
+    sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU8, Ordering},
+};
+use embeddedsw_sys as esys;
+
+use crate::xscugic::interrupt_free;
+
+//-------------------------------------------------------------------------------------------------
+// AssertPolicy
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the behaviors on assertion failures of the drivers.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AssertPolicy {
+    /// Panic with the file and the line of the assertion.
+    Panic = 0,
+    /// Record the failure and return from the driver function,
+    /// so the caller can check it by `take_failure`.
+    Record = 1,
+}
+
+/// The location of a failed assertion in the driver sources.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct AssertFailure {
+    pub file: &'static str,
+    pub line: i32,
+}
+
+impl core::fmt::Display for AssertFailure {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Xil_Assert failed at {}:{}", self.file, self.line)
+    }
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(AssertPolicy::Panic as u8);
+static FILE: AtomicPtr<esys::char8> = AtomicPtr::new(ptr::null_mut());
+static LINE: AtomicI32 = AtomicI32::new(0);
+/// Whether `FILE` and `LINE` hold a failure which is not taken yet.
+static FAILED: AtomicBool = AtomicBool::new(false);
+
+//-------------------------------------------------------------------------------------------------
+// Callback
+//-------------------------------------------------------------------------------------------------
+
+/// This function installs the callback which `Xil_Assert` calls
+/// when an assertion of a driver fails.
+///
+/// With `Panic`, the failure panics instead of spinning forever.
+/// With `Record`, `Xil_AssertWait` is cleared, so the driver function
+/// returns after the failure is recorded.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::xil_assert::{self, AssertPolicy};
+///
+/// xil_assert::install(AssertPolicy::Record);
+/// // call a driver
+/// if let Some(failure) = xil_assert::take_failure() {
+///     println!("{}", failure);
+/// }
+/// ```
+pub fn install(policy: AssertPolicy) {
+    POLICY.store(policy as u8, Ordering::Release);
+    unsafe {
+        ptr::write_volatile(
+            addr_of_mut!(esys::Xil_AssertWait),
+            match policy {
+                AssertPolicy::Panic => 1,
+                AssertPolicy::Record => 0,
+            },
+        );
+        esys::Xil_AssertSetCallback(Some(assert_callback));
+    }
+}
+
+/// This function removes the callback and restores the default behavior,
+/// which spins forever on failures.
+pub fn uninstall() {
+    unsafe {
+        esys::Xil_AssertSetCallback(None);
+        ptr::write_volatile(addr_of_mut!(esys::Xil_AssertWait), 1);
+    }
+}
+
+unsafe extern "C" fn assert_callback(file: *const esys::char8, line: i32) {
+    if POLICY.load(Ordering::Acquire) == AssertPolicy::Panic as u8 {
+        let failure = AssertFailure {
+            file: file_name(file),
+            line,
+        };
+        panic!("{}", failure);
+    }
+    FILE.store(file as *mut _, Ordering::Release);
+    LINE.store(line, Ordering::Release);
+    FAILED.store(true, Ordering::Release);
+}
+
+/// `Xil_Assert` passes `__FILE__`, which is a static string.
+unsafe fn file_name(file: *const esys::char8) -> &'static str {
+    if file.is_null() {
+        return "<unknown>";
+    }
+    CStr::from_ptr(file as *const _)
+        .to_str()
+        .unwrap_or("<invalid>")
+}
+
+//-------------------------------------------------------------------------------------------------
+// Status
+//-------------------------------------------------------------------------------------------------
+
+/// This function checks whether an assertion has failed
+/// since the failure was cleared or taken.
+///
+/// `Xil_AssertStatus` is reset by the next passing assertion,
+/// so the failure recorded by the callback is checked as well.
+pub fn status() -> bool {
+    FAILED.load(Ordering::Acquire)
+        || unsafe {
+            ptr::read_volatile(addr_of!(esys::Xil_AssertStatus))
+                != esys::XIL_ASSERT_NONE
+        }
+}
+
+/// This function clears `Xil_AssertStatus` and the recorded failure.
+pub fn clear() {
+    unsafe {
+        ptr::write_volatile(
+            addr_of_mut!(esys::Xil_AssertStatus),
+            esys::XIL_ASSERT_NONE,
+        );
+    }
+    FAILED.store(false, Ordering::Release);
+    FILE.store(ptr::null_mut(), Ordering::Release);
+}
+
+/// This function takes the failure recorded with the `Record` policy,
+/// and clears it.
+/// If no failure has been recorded since it was taken, it returns None,
+/// even if assertions passed after the failure.
+pub fn take_failure() -> Option<AssertFailure> {
+    let (file, line) = interrupt_free(|| {
+        if !FAILED.swap(false, Ordering::AcqRel) {
+            return None;
+        }
+        Some((FILE.load(Ordering::Acquire), LINE.load(Ordering::Acquire)))
+    })?;
+    clear();
+    Some(AssertFailure {
+        file: unsafe { file_name(file) },
+        line,
+    })
+}
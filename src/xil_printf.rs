@@ -1,6 +1,4 @@
-extern crate embeddedsw_sys;
 use core::fmt::{self, Write};
-use embeddedsw_sys as esys;
 
 /// Print to the UART
 /// This macro uses Rust's formatter, so it can print floating point numbers.
@@ -13,25 +11,52 @@ macro_rules! print {
 /// This macro uses Rust's formatter, so it can print floating point numbers.
 #[macro_export]
 macro_rules! println {
-    ($fmt:expr) => ($crate::print!(concat!($fmt, "\n\r")));
-    ($fmt:expr, $($arg:tt)*) => ($crate::print!(concat!($fmt, "\n\r"), $($arg)*));
+    () => ($crate::print!("\n"));
+    ($fmt:expr) => ($crate::print!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::print!(concat!($fmt, "\n"), $($arg)*));
 }
 
-#[derive(Debug)]
-struct UartWriter;
+/// Print to the stderr UART of the console
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => ($crate::xil_printf::_eprint(format_args!($($arg)*)));
+}
 
-pub fn _print(args: fmt::Arguments) {
-    let mut writer = UartWriter {};
-    writer.write_fmt(args).unwrap();
+/// Print to the stderr UART of the console, with a newline
+#[macro_export]
+macro_rules! eprintln {
+    () => ($crate::eprint!("\n"));
+    ($fmt:expr) => ($crate::eprint!(concat!($fmt, "\n")));
+    ($fmt:expr, $($arg:tt)*) => ($crate::eprint!(concat!($fmt, "\n"), $($arg)*));
 }
 
-impl Write for UartWriter {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.bytes() {
-            unsafe {
-                esys::outbyte(c);
-            };
+/// Print the file, the line and the values of the expressions
+/// to the stderr UART, and return the values like `std::dbg!`.
+#[macro_export]
+macro_rules! dbg {
+    () => {
+        $crate::eprintln!("[{}:{}:{}]", file!(), line!(), column!())
+    };
+    ($val:expr $(,)?) => {
+        match $val {
+            tmp => {
+                $crate::eprintln!("[{}:{}:{}] {} = {:#?}",
+                    file!(), line!(), column!(), stringify!($val), &tmp);
+                tmp
+            }
         }
-        Ok(())
-    }
+    };
+    ($($val:expr),+ $(,)?) => {
+        ($($crate::dbg!($val)),+,)
+    };
+}
+
+pub fn _print(args: fmt::Arguments) {
+    let mut out = crate::console::stdout().lock();
+    out.write_fmt(args).unwrap();
+}
+
+pub fn _eprint(args: fmt::Arguments) {
+    let mut out = crate::console::stderr().lock();
+    out.write_fmt(args).unwrap();
 }
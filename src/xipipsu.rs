@@ -0,0 +1,330 @@
+extern crate embeddedsw_sys;
+use core::mem::MaybeUninit;
+use core::ops::BitOr;
+use embeddedsw_sys as esys;
+
+use crate::xscugic::RawInterrupt;
+use crate::xstatus::{self, XStatus};
+
+//-------------------------------------------------------------------------------------------------
+// IpiMask
+//-------------------------------------------------------------------------------------------------
+
+/// The IPI channel mask of source or destination processors.
+///
+/// The bit positions are fixed by the ZynqMP IPI block,
+/// so they are the same values as `XPAR_XIPIPS_TARGET_*_MASK` in xparameters.h.
+/// The masks can be combined with `|`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct IpiMask(u32);
+
+impl IpiMask {
+    /// APU (Cortex-A53), channel 0
+    pub const APU: IpiMask = IpiMask(0x0000_0001);
+    /// RPU core 0 (Cortex-R5 0), channel 1
+    pub const R5_0: IpiMask = IpiMask(0x0000_0100);
+    /// RPU core 1 (Cortex-R5 1), channel 2
+    pub const R5_1: IpiMask = IpiMask(0x0000_0200);
+    /// PMU, channel 3
+    pub const PMU_0: IpiMask = IpiMask(0x0001_0000);
+    /// PMU, channel 4
+    pub const PMU_1: IpiMask = IpiMask(0x0002_0000);
+    /// PMU, channel 5
+    pub const PMU_2: IpiMask = IpiMask(0x0004_0000);
+    /// PMU, channel 6
+    pub const PMU_3: IpiMask = IpiMask(0x0008_0000);
+    /// PL, channel 7
+    pub const PL_0: IpiMask = IpiMask(0x0100_0000);
+    /// PL, channel 8
+    pub const PL_1: IpiMask = IpiMask(0x0200_0000);
+    /// PL, channel 9
+    pub const PL_2: IpiMask = IpiMask(0x0400_0000);
+    /// PL, channel 10
+    pub const PL_3: IpiMask = IpiMask(0x0800_0000);
+
+    /// This function creates a mask from raw bits.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// This function takes raw bits of the mask.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// This function checks whether the mask has no channel.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// This function checks whether the mask contains the other mask.
+    pub const fn contains(self, other: IpiMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for IpiMask {
+    type Output = IpiMask;
+
+    fn bitor(self, rhs: IpiMask) -> IpiMask {
+        IpiMask(self.0 | rhs.0)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// IpiBuffer
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for IPI message buffer types.
+///
+/// Message:  the buffer which the source writes a request into.
+/// Response: the buffer which the destination writes a response into.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IpiBuffer {
+    Message = esys::XIPIPSU_BUF_TYPE_MSG as u8,
+    Response = esys::XIPIPSU_BUF_TYPE_RESP as u8,
+}
+
+/// The maximum length of a message in words (32 bytes).
+pub const MAX_MSG_LEN: usize = esys::XIPIPSU_MAX_MSG_LEN as usize;
+
+//-------------------------------------------------------------------------------------------------
+// XIpiPsuConfig
+//-------------------------------------------------------------------------------------------------
+
+/// The configuration structure for IPI instance.
+#[repr(C)]
+pub struct XIpiPsuConfig {
+    config: *mut esys::XIpiPsu_Config,
+}
+
+impl XIpiPsuConfig {
+    /// Look up the hardware configuration for XIpiPsu instance.
+    ///
+    /// # Errors
+    /// If this function cannot find device configuration,
+    /// it returns XST_DEVICE_NOT_FOUND.
+    pub fn lookup_config(id: u32) -> Result<Self, XStatus> {
+        let config = unsafe { esys::XIpiPsu_LookupConfig(id) };
+
+        if config.is_null() {
+            Err(XStatus::DeviceNotFound)
+        } else {
+            Ok(Self { config })
+        }
+    }
+
+    /// This function takes a base address of the IPI channel.
+    pub fn get_base_addr(&self) -> usize {
+        unsafe { (*self.config).BaseAddress as usize }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// XIpiPsu
+//-------------------------------------------------------------------------------------------------
+
+/// The IPI instance struct
+///
+/// # Example
+/// Core 0 sends a message to core 1 in split mode.
+/// ```
+/// use embeddedsw_rs::{
+///     peripherals::Peripherals,
+///     raw,
+///     xipipsu::{IpiBuffer, IpiMask},
+/// };
+///
+/// let mut p = Peripherals::take().unwrap();
+/// let ipi = p.ipipsu(raw::XPAR_XIPIPSU_0_DEVICE_ID).unwrap();
+///
+/// ipi.write_message(IpiMask::R5_1, &[0xdead_beef], IpiBuffer::Message).unwrap();
+/// ipi.trigger(IpiMask::R5_1).unwrap();
+/// ipi.poll_for_ack(IpiMask::R5_1, 10_000).unwrap();
+/// ```
+///
+/// Core 1 receives it in the handler connected to `XIpiPsu::int_id()`
+/// through `XScuGic::connect`.
+/// ```
+/// let sources = ipi.interrupt_status();
+/// if sources.contains(IpiMask::R5_0) {
+///     let mut msg = [0; 1];
+///     ipi.read_message(IpiMask::R5_0, &mut msg, IpiBuffer::Message).unwrap();
+///     ipi.clear_interrupt(IpiMask::R5_0);
+/// }
+/// ```
+#[repr(C)]
+pub struct XIpiPsu {
+    inner: esys::XIpiPsu,
+}
+
+impl XIpiPsu {
+    /// This function initializes a XIpiPsu struct.
+    /// Take an initialized instance with `Peripherals::ipipsu`.
+    ///
+    /// # Errors
+    /// If this function cannot initialize the instance,
+    /// it returns the XST_* status code.
+    pub(crate) fn cfg_initialize(
+        xipipsu: &mut MaybeUninit<XIpiPsu>,
+        config: &XIpiPsuConfig,
+    ) -> Result<(), XStatus> {
+        let status = unsafe {
+            esys::XIpiPsu_CfgInitialize(
+                xipipsu.as_mut_ptr() as *mut esys::XIpiPsu,
+                config.config,
+                config.get_base_addr() as _,
+            )
+        };
+
+        xstatus::check(status)
+    }
+
+    /// This function resets the IPI channel.
+    /// All interrupts are disabled and cleared.
+    pub fn reset(&mut self) {
+        unsafe { esys::XIpiPsu_Reset(&mut self.inner) }
+    }
+
+    /// This function takes the interrupt ID of this IPI channel.
+    /// Connect a handler to it with `XScuGic::connect`.
+    pub fn int_id(&self) -> RawInterrupt {
+        RawInterrupt(self.inner.Config.IntId)
+    }
+
+    /// This function triggers IPIs to the destinations.
+    pub fn trigger(&mut self, dest: IpiMask) -> Result<(), XStatus> {
+        let status =
+            unsafe { esys::XIpiPsu_TriggerIpi(&mut self.inner, dest.bits()) };
+
+        xstatus::check(status)
+    }
+
+    /// This function waits until the destinations acknowledge
+    /// the IPIs by clearing their interrupt status.
+    ///
+    /// # Errors
+    /// If the destinations do not acknowledge in the timeout count,
+    /// it returns XST_FAILURE.
+    pub fn poll_for_ack(
+        &self,
+        dest: IpiMask,
+        timeout: u32,
+    ) -> Result<(), XStatus> {
+        let status = unsafe {
+            esys::XIpiPsu_PollForAck(&self.inner, dest.bits(), timeout)
+        };
+
+        xstatus::check(status)
+    }
+
+    /// This function writes a message into the buffer
+    /// shared with the destination.
+    ///
+    /// # Errors
+    /// If the message is longer than `MAX_MSG_LEN` words,
+    /// it returns XST_INVALID_PARAM.
+    pub fn write_message(
+        &mut self,
+        dest: IpiMask,
+        msg: &[u32],
+        buffer: IpiBuffer,
+    ) -> Result<(), XStatus> {
+        if msg.len() > MAX_MSG_LEN {
+            return Err(XStatus::InvalidParam);
+        }
+
+        let status = unsafe {
+            esys::XIpiPsu_WriteMessage(
+                &mut self.inner,
+                dest.bits(),
+                msg.as_ptr() as *mut u32,
+                msg.len() as u32,
+                buffer as u8,
+            )
+        };
+
+        xstatus::check(status)
+    }
+
+    /// This function reads a message from the buffer
+    /// shared with the source.
+    ///
+    /// # Errors
+    /// If the message is longer than `MAX_MSG_LEN` words,
+    /// it returns XST_INVALID_PARAM.
+    pub fn read_message(
+        &mut self,
+        src: IpiMask,
+        msg: &mut [u32],
+        buffer: IpiBuffer,
+    ) -> Result<(), XStatus> {
+        if msg.len() > MAX_MSG_LEN {
+            return Err(XStatus::InvalidParam);
+        }
+
+        let status = unsafe {
+            esys::XIpiPsu_ReadMessage(
+                &mut self.inner,
+                src.bits(),
+                msg.as_mut_ptr(),
+                msg.len() as u32,
+                buffer as u8,
+            )
+        };
+
+        xstatus::check(status)
+    }
+
+    /// This function takes the sources which have triggered IPIs.
+    pub fn interrupt_status(&self) -> IpiMask {
+        IpiMask(unsafe {
+            Self::read_reg(self.base_addr(), esys::XIPIPSU_ISR_OFFSET)
+        })
+    }
+
+    /// This function acknowledges the IPIs from the sources.
+    pub fn clear_interrupt(&mut self, src: IpiMask) {
+        unsafe {
+            Self::write_reg(self.base_addr(), esys::XIPIPSU_ISR_OFFSET, src.0)
+        }
+    }
+
+    /// This function takes the destinations which have not
+    /// acknowledged the IPIs yet.
+    pub fn observation_status(&self) -> IpiMask {
+        IpiMask(unsafe {
+            Self::read_reg(self.base_addr(), esys::XIPIPSU_OBS_OFFSET)
+        })
+    }
+
+    /// This function enables IPIs from the sources.
+    pub fn interrupt_enable(&mut self, src: IpiMask) {
+        unsafe {
+            Self::write_reg(self.base_addr(), esys::XIPIPSU_IER_OFFSET, src.0)
+        }
+    }
+
+    /// This function disables IPIs from the sources.
+    pub fn interrupt_disable(&mut self, src: IpiMask) {
+        unsafe {
+            Self::write_reg(self.base_addr(), esys::XIPIPSU_IDR_OFFSET, src.0)
+        }
+    }
+
+    #[inline(always)]
+    fn base_addr(&self) -> u32 {
+        self.inner.Config.BaseAddress as u32
+    }
+
+    #[inline(always)]
+    unsafe fn write_reg(base_addr: u32, offset: u32, data: u32) {
+        core::ptr::write_volatile((base_addr + offset) as *mut u32, data)
+    }
+
+    #[inline(always)]
+    unsafe fn read_reg(base_addr: u32, offset: u32) -> u32 {
+        core::ptr::read_volatile((base_addr + offset) as *const u32)
+    }
+}
@@ -1,9 +1,14 @@
 extern crate alloc;
 extern crate embeddedsw_sys;
+use core::cell::UnsafeCell;
 use core::ffi;
+use core::ops::BitOr;
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
 use core::{arch::asm, mem::MaybeUninit, ptr};
 use embeddedsw_sys as esys;
 
+use crate::xstatus::{self, XStatus};
+
 //-------------------------------------------------------------------------------------------------
 // XScuGicConfig
 //-------------------------------------------------------------------------------------------------
@@ -19,13 +24,13 @@ impl XScuGicConfig {
     ///
     /// # Errors
     /// If this function cannot find device configuration,
-    /// it returns ().
+    /// it returns XST_DEVICE_NOT_FOUND.
     ///
-    pub fn lookup_config(id: u16) -> Result<Self, ()> {
+    pub fn lookup_config(id: u16) -> Result<Self, XStatus> {
         let config = unsafe { esys::XScuGic_LookupConfig(id) };
 
         if config.is_null() {
-            return Err(());
+            return Err(XStatus::DeviceNotFound);
         } else {
             Ok(Self { config })
         }
@@ -51,18 +56,16 @@ impl XScuGicConfig {
 /// #![no_main]
 /// #![feature(start)]
 /// extern crate embeddedsw_rs;
-/// use core::mem::MaybeUninit;
-/// use cstr_core::CStr;
 /// use embeddedsw_rs as xemb;
 /// use embeddedsw_rs::{
-///    raw,
-///    xscugic::{self, XScuGic, XScuGicConfig},
+///    peripherals::Peripherals,
+///    xscugic::{self, CpuMask, Interrupt},
 /// };
 /// use xemb::{print, println};
 ///
 /// const INTC_DEVICE_ID: u16 = 0;
-/// const INTC_DEVICE_INT_ID: u16 = 0x0e;
-/// const XSCUGIC_CPU_MASK: u16 = raw::XSCUGIC_SPI_CPU0_MASK;
+/// const INTC_DEVICE_INT_ID: Interrupt = Interrupt::Sgi14;
+/// const XSCUGIC_CPU_MASK: CpuMask = CpuMask::R5_0;
 ///
 /// static mut INTERRUPT_PROCESSED: bool = false;
 ///
@@ -75,32 +78,23 @@ impl XScuGicConfig {
 /// #[start]
 /// fn main(_argc: isize, _argv: *const *const u8) -> isize {
 ///
-///    let xconfig =  XScuGicConfig::lookup_config(INTC_DEVICE_ID).unwrap();
-///
-///    let mut xscu_gic = MaybeUninit::<XScuGic>::uninit();
-///    // cfg initialize
-///    unsafe {
-///        XScuGic::cfg_initialize(
-///            &mut xscu_gic,
-///            &xconfig,
-///            xconfig.get_cpu_base_addr(),
-///        ).unwrap();
-///    }
-///    let mut xscu_gic = unsafe { xscu_gic.assume_init() };
+///    // take the initialized instance
+///    let mut p = Peripherals::take().unwrap();
+///    let xscu_gic = p.scugic(INTC_DEVICE_ID).unwrap();
 ///
 ///    // setupt interrupt system
 ///    xscu_gic.exception_register_handler();
 ///    unsafe { xscugic::xil_exception_enable() };
 ///
 ///    // connect handler
-///    xscu_gic.connect(INTC_DEVICE_INT_ID as u32, Some(device_handler)).unwarap();
+///    xscu_gic.connect(INTC_DEVICE_INT_ID, Some(device_handler)).unwrap();
 ///
 ///    // unable interrupt
-///    xscu_gic.enable(INTC_DEVICE_INT_ID as u32);
+///    xscu_gic.enable(INTC_DEVICE_INT_ID);
 ///
 ///    // software interrupt
 ///    xscu_gic.software_intr(
-///        INTC_DEVICE_INT_ID as u32,
+///        INTC_DEVICE_INT_ID,
 ///        XSCUGIC_CPU_MASK,
 ///    ).unwrap();
 ///
@@ -128,6 +122,10 @@ pub struct XScuGic {
 impl XScuGic {
     /// This function initializes a XScuGic structs.
     ///
+    /// All interrupt sources are assigned to `InterruptGroup::NonSecure`,
+    /// so they are signaled as IRQ until `set_group` or `connect_fiq`
+    /// moves one to FIQ.
+    ///
     /// # Safety
     /// effective_addr is the device base address in the virtual memory address space.
     /// The caller is responsible for keeping the address mapping
@@ -136,27 +134,23 @@ impl XScuGic {
         xscugic: &mut MaybeUninit<XScuGic>,
         config: &XScuGicConfig,
         effective_addr: u32,
-    ) -> Result<(), i32> {
-        let status = esys::XScuGic_CfgInitialize(
+    ) -> Result<(), XStatus> {
+        xstatus::check(esys::XScuGic_CfgInitialize(
             xscugic.as_mut_ptr() as *mut esys::XScuGic,
             config.config,
             effective_addr,
-        );
-
-        if status != 0 {
-            return Err(status);
-        } else {
-            Ok(())
-        }
+        ))?;
+        xscugic.assume_init_mut().set_all_non_secure();
+        Ok(())
     }
 
     /// This function makes the connections between the ID of interrupt source
     /// and the corresponding handler.
     pub fn connect(
         &mut self,
-        id: u32,
+        id: impl InterruptSource,
         handler: Option<extern "C" fn() -> ()>,
-    ) -> Result<(), i32> {
+    ) -> Result<(), XStatus> {
         let handler = handler.map(|f| unsafe {
             let ptr = f as *const _;
             core::mem::transmute::<
@@ -167,31 +161,118 @@ impl XScuGic {
         let status = unsafe {
             esys::XScuGic_Connect(
                 &mut self.inner,
-                id,
+                id.interrupt_id(),
                 handler,
                 ptr::null::<()>() as *mut _,
             )
         };
-        if status != 0 {
-            Err(status)
-        } else {
-            Ok(())
-        }
+        xstatus::check(status)
     }
 
     /// This function disconnects the registerd handler corresponding to the interrupt ID.
-    pub fn disconnect(&mut self, id: u32) {
+    pub fn disconnect(&mut self, id: impl InterruptSource) {
+        let id = id.interrupt_id();
         unsafe { esys::XScuGic_Disconnect(&mut self.inner, id) };
     }
 
     /// This function enables the interrupt source provided as the id.
-    pub fn enable(&mut self, id: u32) {
+    pub fn enable(&mut self, id: impl InterruptSource) {
+        let id = id.interrupt_id();
         unsafe { esys::XScuGic_Enable(&mut self.inner, id) };
     }
 
     /// This function diables the interrupt source provided as the id.
-    pub fn disable(&mut self, id: u32) {
-        unsafe { esys::XScuGic_Enable(&mut self.inner, id) };
+    pub fn disable(&mut self, id: impl InterruptSource) {
+        let id = id.interrupt_id();
+        unsafe { esys::XScuGic_Disable(&mut self.inner, id) };
+    }
+
+    /// This function sets the priority and the trigger type
+    /// of the interrupt source provided as the id.
+    ///
+    /// Lower values mean higher priorities.
+    /// Only upper 5 bits of the priority are used, so use multiples of 8.
+    pub fn set_priority_trigger_type(
+        &mut self,
+        id: impl InterruptSource,
+        priority: u8,
+        trigger: Trigger,
+    ) {
+        unsafe {
+            esys::XScuGic_SetPriorityTriggerType(
+                &mut self.inner,
+                id.interrupt_id(),
+                priority,
+                trigger as u8,
+            )
+        }
+    }
+
+    /// This function takes the priority and the trigger type
+    /// of the interrupt source provided as the id.
+    pub fn get_priority_trigger_type(
+        &mut self,
+        id: impl InterruptSource,
+    ) -> (u8, Trigger) {
+        let mut priority = 0;
+        let mut trigger = 0;
+        unsafe {
+            esys::XScuGic_GetPriorityTriggerType(
+                &mut self.inner,
+                id.interrupt_id(),
+                &mut priority,
+                &mut trigger,
+            )
+        };
+
+        let trigger = if trigger & 0b10 == 0 {
+            Trigger::Level
+        } else {
+            Trigger::RisingEdge
+        };
+        (priority, trigger)
+    }
+
+    /// This function sets the priority mask of the CPU interface.
+    /// Only interrupts which have higher priorities (lower values)
+    /// than the mask are signaled to the CPU.
+    pub fn set_priority_mask(&self, mask: u8) {
+        unsafe {
+            Self::write_reg(
+                self.cpu_base_addr(),
+                esys::XSCUGIC_CPU_PRIOR_OFFSET,
+                mask as u32,
+            )
+        }
+    }
+
+    /// This function takes the priority mask of the CPU interface.
+    pub fn get_priority_mask(&self) -> u8 {
+        unsafe {
+            Self::read_reg(self.cpu_base_addr(), esys::XSCUGIC_CPU_PRIOR_OFFSET)
+                as u8
+        }
+    }
+
+    /// This function generates a software interrupt (SGI)
+    /// to the CPUs provided as the cpus.
+    ///
+    /// # Errors
+    /// If the id is not a SGI (0 ~ 15), it returns XST_INVALID_PARAM.
+    pub fn software_intr(
+        &mut self,
+        id: impl InterruptSource,
+        cpus: CpuMask,
+    ) -> Result<(), XStatus> {
+        let id = id.interrupt_id();
+        if id > MAX_SGI_ID {
+            return Err(XStatus::InvalidParam);
+        }
+
+        let status = unsafe {
+            esys::XScuGic_SoftwareIntr(&mut self.inner, id, cpus.bits())
+        };
+        xstatus::check(status)
     }
 
     /// This function registers the handler.
@@ -238,14 +319,354 @@ impl XScuGic {
         Self::xil_in32(base_addr + offset)
     }
 
+    /// This function takes the CPU interface base address of this instance.
+    #[inline(always)]
+    pub(crate) fn cpu_base_addr(&self) -> u32 {
+        unsafe { (*self.inner.Config).CpuBaseAddress }
+    }
+
+    /// This function takes the distributor base address of this instance.
+    #[inline(always)]
+    fn dist_base_addr(&self) -> u32 {
+        unsafe { (*self.inner.Config).DistBaseAddress }
+    }
+
+    /// This function enables FIQ interrupt.
+    /// After calling this function, every interrupt assigned to
+    /// `InterruptGroup::Secure` is signaled as FIQ instead of IRQ.
+    #[inline(always)]
+    pub fn enable_fiq(&self) {
+        let base_addr = self.cpu_base_addr();
+        unsafe {
+            let reg = Self::read_reg(base_addr, esys::XSCUGIC_CONTROL_OFFSET);
+            Self::write_reg(
+                base_addr,
+                esys::XSCUGIC_CONTROL_OFFSET,
+                reg | esys::XSCUGIC_CNTR_FIQEN_MASK,
+            );
+        }
+    }
+
     /// This function diable FIQ interrupt.
     #[inline(always)]
     pub fn disable_fiq(&self) {
+        let base_addr = self.cpu_base_addr();
+        unsafe {
+            let reg = Self::read_reg(base_addr, esys::XSCUGIC_CONTROL_OFFSET);
+            Self::write_reg(
+                base_addr,
+                esys::XSCUGIC_CONTROL_OFFSET,
+                reg & !esys::XSCUGIC_CNTR_FIQEN_MASK,
+            );
+        }
+    }
+
+    /// This function assigns the interrupt source provided as the id
+    /// to the interrupt group.
+    ///
+    /// `cfg_initialize` assigns every source to `InterruptGroup::NonSecure`,
+    /// and only the sources set to `InterruptGroup::Secure` are signaled
+    /// as FIQ after `enable_fiq`.
+    pub fn set_group(
+        &mut self,
+        id: impl InterruptSource,
+        group: InterruptGroup,
+    ) {
+        let id = id.interrupt_id();
+        let base_addr = self.dist_base_addr();
+        let offset = esys::XSCUGIC_SECURITY_OFFSET + (id / 32) * 4;
+        let mask = 1 << (id % 32);
+        unsafe {
+            let reg = Self::read_reg(base_addr, offset);
+            match group {
+                InterruptGroup::Secure => {
+                    Self::write_reg(base_addr, offset, reg & !mask)
+                }
+                InterruptGroup::NonSecure => {
+                    Self::write_reg(base_addr, offset, reg | mask);
+                    Self::enable_non_secure(base_addr);
+                }
+            }
+        }
+    }
+
+    /// This function assigns all interrupt sources to
+    /// `InterruptGroup::NonSecure`.
+    fn set_all_non_secure(&mut self) {
+        let base_addr = self.dist_base_addr();
+        let num = esys::XSCUGIC_MAX_NUM_INTR_INPUTS.div_ceil(32);
         unsafe {
-            let reg = Self::read_reg(esys::XPAR_PSU_RCPU_GIC_BASEADDR, 0);
-            Self::write_reg(esys::XPAR_PSU_RCPU_GIC_BASEADDR, 0, reg & !8);
+            for i in 0..num {
+                let offset = esys::XSCUGIC_SECURITY_OFFSET + i * 4;
+                Self::write_reg(base_addr, offset, u32::MAX);
+            }
+            Self::enable_non_secure(base_addr);
         }
     }
+
+    /// The distributor forwards non-secure interrupts
+    /// only if the non-secure enable bit is set.
+    unsafe fn enable_non_secure(dist_base_addr: u32) {
+        let ctrl = Self::read_reg(dist_base_addr, esys::XSCUGIC_DIST_EN_OFFSET);
+        Self::write_reg(
+            dist_base_addr,
+            esys::XSCUGIC_DIST_EN_OFFSET,
+            ctrl | DIST_EN_NS_MASK,
+        );
+    }
+
+    /// This function takes the interrupt group of the interrupt source
+    /// provided as the id.
+    pub fn get_group(&self, id: impl InterruptSource) -> InterruptGroup {
+        let id = id.interrupt_id();
+        let offset = esys::XSCUGIC_SECURITY_OFFSET + (id / 32) * 4;
+        let reg = unsafe { Self::read_reg(self.dist_base_addr(), offset) };
+        if reg & (1 << (id % 32)) == 0 {
+            InterruptGroup::Secure
+        } else {
+            InterruptGroup::NonSecure
+        }
+    }
+
+    /// This function registers the handler for FIQ exception.
+    ///
+    /// FIQ interrupts are dispatched through the same handler table as IRQ,
+    /// so handlers registered by `connect` are called for FIQ sources too.
+    pub fn exception_register_fiq_handler(&mut self) {
+        unsafe {
+            let xscu_interrupt_handler =
+                Some(esys::XScuGic_InterruptHandler as *const _).map(|f| {
+                    core::mem::transmute::<
+                        *const (),
+                        unsafe extern "C" fn(*mut ffi::c_void) -> (),
+                    >(f)
+                });
+
+            esys::Xil_ExceptionRegisterHandler(
+                esys::XIL_EXCEPTION_ID_FIQ_INT,
+                xscu_interrupt_handler,
+                &mut self.inner as *mut esys::XScuGic as *mut _,
+            )
+        }
+    }
+
+    /// This function moves one interrupt source to FIQ with a dedicated handler.
+    ///
+    /// The handler is called directly from the FIQ exception without looking up
+    /// the handler table, so it has the lowest latency.
+    /// Only one source can be connected by this function at a time.
+    /// Only the group of this source is changed to `InterruptGroup::Secure`,
+    /// so the groups of the other sources are kept.
+    /// Give the source the highest priority, so the FIQ handler does not
+    /// find another interrupt first.
+    /// Call `enable_fiq` and `xil_exception_enable_fiq` to start receiving it.
+    pub fn connect_fiq(
+        &mut self,
+        id: impl InterruptSource,
+        handler: extern "C" fn() -> (),
+    ) {
+        let id = id.interrupt_id();
+        FIQ_HANDLER.store(handler as usize, Ordering::Release);
+        FIQ_ID.store(id as usize, Ordering::Release);
+        FIQ_DIST_BASE.store(self.dist_base_addr(), Ordering::Release);
+        self.set_group(RawInterrupt(id), InterruptGroup::Secure);
+
+        unsafe {
+            esys::Xil_ExceptionRegisterHandler(
+                esys::XIL_EXCEPTION_ID_FIQ_INT,
+                Some(fiq_handler),
+                self.cpu_base_addr() as *mut _,
+            )
+        }
+    }
+
+    /// This function remove the registerd FIQ handler.
+    pub fn exception_remove_fiq_handler(&self) {
+        FIQ_HANDLER.store(0, Ordering::Release);
+        FIQ_ID.store(SPURIOUS_ID as usize, Ordering::Release);
+        unsafe {
+            esys::Xil_ExceptionRemoveHandler(esys::XIL_EXCEPTION_ID_FIQ_INT)
+        };
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Interrupt IDs
+//-------------------------------------------------------------------------------------------------
+
+/// Interrupt IDs generated from xparameters.h of the hardware design.
+///
+/// The variants are named after `XPAR_*_INTR` and `XPS_*_INT_ID` macros,
+/// e.g. `XPAR_FABRIC_AXI_DMA_0_MM2S_INTROUT_INTR` is `FabricAxiDma0Mm2sIntrout`
+/// and `XPS_UART0_INT_ID` is `Uart0`. `Sgi0` ~ `Sgi15` are software
+/// generated interrupts.
+pub use esys::interrupt::Interrupt;
+
+/// A trait for the types which identify an interrupt source.
+pub trait InterruptSource {
+    /// This function takes the interrupt ID of the source.
+    fn interrupt_id(self) -> u32;
+}
+
+impl InterruptSource for Interrupt {
+    #[inline(always)]
+    fn interrupt_id(self) -> u32 {
+        self.id()
+    }
+}
+
+/// A raw interrupt ID for the sources which are not listed in `Interrupt`.
+///
+/// ```
+/// use embeddedsw_rs::xscugic::RawInterrupt;
+///
+/// xscu_gic.enable(RawInterrupt(0x0e + 32));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RawInterrupt(pub u32);
+
+impl InterruptSource for RawInterrupt {
+    #[inline(always)]
+    fn interrupt_id(self) -> u32 {
+        self.0
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Trigger
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for interrupt trigger types.
+#[repr(u8)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Trigger {
+    /// Active high level sensitive
+    Level = 0b01,
+    /// Rising edge sensitive
+    RisingEdge = 0b11,
+}
+
+//-------------------------------------------------------------------------------------------------
+// CpuMask
+//-------------------------------------------------------------------------------------------------
+
+/// The largest interrupt ID of software generated interrupts.
+const MAX_SGI_ID: u32 = 15;
+
+/// The target CPU list of software generated interrupts.
+///
+/// The masks can be combined with `|`.
+/// ```
+/// use embeddedsw_rs::xscugic::CpuMask;
+///
+/// let both = CpuMask::R5_0 | CpuMask::R5_1;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CpuMask(u32);
+
+impl CpuMask {
+    /// CPU 0 of the GIC, i.e. RPU core 0 in split mode.
+    pub const CPU0: CpuMask = CpuMask(esys::XSCUGIC_SPI_CPU0_MASK);
+    /// CPU 1 of the GIC, i.e. RPU core 1 in split mode.
+    pub const CPU1: CpuMask = CpuMask(esys::XSCUGIC_SPI_CPU1_MASK);
+    /// Alias of `CPU0`.
+    pub const R5_0: CpuMask = Self::CPU0;
+    /// Alias of `CPU1`.
+    pub const R5_1: CpuMask = Self::CPU1;
+
+    /// This function creates a mask from raw bits.
+    /// Use it for the CPUs which are not listed as constants.
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// This function takes raw bits of the mask.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// This function checks whether the mask contains the other mask.
+    pub const fn contains(self, other: CpuMask) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl BitOr for CpuMask {
+    type Output = CpuMask;
+
+    fn bitor(self, rhs: CpuMask) -> CpuMask {
+        CpuMask(self.0 | rhs.0)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// FIQ
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for interrupt groups.
+///
+/// Secure:    Group 0, signaled as FIQ when FIQ is enabled.
+/// NonSecure: Group 1, always signaled as IRQ.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum InterruptGroup {
+    Secure,
+    NonSecure,
+}
+
+/// Non-secure enable bit of the distributor control register.
+const DIST_EN_NS_MASK: u32 = 0x2;
+
+/// The handler connected by `XScuGic::connect_fiq`.
+static FIQ_HANDLER: AtomicUsize = AtomicUsize::new(0);
+/// The interrupt ID connected by `XScuGic::connect_fiq`.
+static FIQ_ID: AtomicUsize = AtomicUsize::new(SPURIOUS_ID as usize);
+/// The distributor base address of the GIC of `XScuGic::connect_fiq`.
+static FIQ_DIST_BASE: AtomicU32 = AtomicU32::new(0);
+
+/// IDs 1020 to 1023 are special, which are not acknowledged.
+const SPECIAL_ID_START: u32 = 1020;
+const SPURIOUS_ID: u32 = 1023;
+
+/// FIQ exception handler which acknowledges the interrupt
+/// and calls the handler connected by `XScuGic::connect_fiq`.
+///
+/// Reading the acknowledge register can also acknowledge an IRQ source,
+/// so it is read only when the highest pending interrupt is the FIQ source.
+unsafe extern "C" fn fiq_handler(cpu_base_addr: *mut ffi::c_void) {
+    let base_addr = cpu_base_addr as u32;
+    let fiq_id = FIQ_ID.load(Ordering::Acquire);
+    let pending = XScuGic::read_reg(base_addr, esys::XSCUGIC_HI_PEND_OFFSET)
+        & esys::XSCUGIC_ACK_INTID_MASK;
+    if pending as usize != fiq_id {
+        // the IRQ handler takes it.
+        return;
+    }
+
+    let iar = XScuGic::read_reg(base_addr, esys::XSCUGIC_INT_ACK_OFFSET);
+    let id = iar & esys::XSCUGIC_ACK_INTID_MASK;
+    if id >= SPECIAL_ID_START {
+        // no interrupt was acknowledged, so EOI must not be written.
+        return;
+    }
+
+    if id as usize == fiq_id {
+        let handler = FIQ_HANDLER.load(Ordering::Acquire);
+        if handler != 0 {
+            let handler =
+                core::mem::transmute::<usize, extern "C" fn() -> ()>(handler);
+            handler();
+        }
+    } else {
+        // a higher priority interrupt arrived after the check,
+        // so it is made pending again for the IRQ handler.
+        XScuGic::write_reg(
+            FIQ_DIST_BASE.load(Ordering::Acquire),
+            esys::XSCUGIC_PENDING_SET_OFFSET + (id / 32) * 4,
+            1 << (id % 32),
+        );
+    }
+
+    XScuGic::write_reg(base_addr, esys::XSCUGIC_EOI_OFFSET, iar);
 }
 
 //-------------------------------------------------------------------------------------------------
@@ -254,14 +675,14 @@ impl XScuGic {
 //-------------------------------------------------------------------------------------------------
 
 #[inline(always)]
-unsafe fn mfcpsr() -> u32 {
+pub(crate) unsafe fn mfcpsr() -> u32 {
     let mut rval;
     asm!("mrs {}, cpsr", out(reg) rval, options(preserves_flags));
     rval
 }
 
 #[inline(always)]
-unsafe fn mtcpsr(v: u32) {
+pub(crate) unsafe fn mtcpsr(v: u32) {
     asm!("msr cpsr, {}", in(reg) v)
 }
 
@@ -270,7 +691,98 @@ unsafe fn xil_exception_enable_mask(mask: u32) {
     mtcpsr(mfcpsr() & !(mask & esys::XIL_EXCEPTION_ALL))
 }
 
+#[inline(always)]
+unsafe fn xil_exception_disable_mask(mask: u32) {
+    mtcpsr(mfcpsr() | (mask & esys::XIL_EXCEPTION_ALL))
+}
+
 #[inline(always)]
 pub unsafe fn xil_exception_enable() {
     xil_exception_enable_mask(esys::XIL_EXCEPTION_IRQ)
 }
+
+#[inline(always)]
+pub unsafe fn xil_exception_disable() {
+    xil_exception_disable_mask(esys::XIL_EXCEPTION_IRQ)
+}
+
+#[inline(always)]
+pub unsafe fn xil_exception_enable_fiq() {
+    xil_exception_enable_mask(esys::XIL_EXCEPTION_FIQ)
+}
+
+#[inline(always)]
+pub unsafe fn xil_exception_disable_fiq() {
+    xil_exception_disable_mask(esys::XIL_EXCEPTION_FIQ)
+}
+
+/// This function puts the processor into low-power state
+/// until an interrupt (or FIQ) becomes pending.
+///
+/// It wakes up even if IRQ is masked by CPSR,
+/// so it can be called in `interrupt_free` to avoid lost wake-ups.
+#[inline(always)]
+pub fn wait_for_interrupt() {
+    unsafe { asm!("dsb", "wfi") }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Critical section
+//-------------------------------------------------------------------------------------------------
+
+/// This function runs the closure with IRQ and FIQ masked,
+/// and restores the previous mask after that.
+/// It can be nested and called from interrupt handlers.
+#[inline(always)]
+pub fn interrupt_free<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    unsafe {
+        let cpsr = mfcpsr();
+        mtcpsr(cpsr | esys::XIL_EXCEPTION_ALL);
+        let r = f();
+        mtcpsr(
+            (mfcpsr() & !esys::XIL_EXCEPTION_ALL)
+                | (cpsr & esys::XIL_EXCEPTION_ALL),
+        );
+        r
+    }
+}
+
+/// A mutex which protects data shared with interrupt handlers
+/// by masking IRQ and FIQ.
+pub struct Mutex<T> {
+    inner: UnsafeCell<T>,
+    locked: AtomicBool,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    /// This function creates a new mutex.
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: UnsafeCell::new(value),
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// This function runs the closure with exclusive access to the data.
+    ///
+    /// # Panics
+    /// If `lock` of the same mutex is called in the closure, it panics.
+    pub fn lock<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        interrupt_free(|| {
+            if self.locked.swap(true, Ordering::Acquire) {
+                panic!("Mutex is locked recursively");
+            }
+            let r = f(unsafe { &mut *self.inner.get() });
+            self.locked.store(false, Ordering::Release);
+            r
+        })
+    }
+}
@@ -0,0 +1,98 @@
+extern crate embeddedsw_sys;
+use embeddedsw_sys as esys;
+
+#[cfg(feature = "xilffs")]
+use crate::ff::FResult;
+use crate::peripherals::PeripheralError;
+#[cfg(feature = "xaxidma")]
+use crate::xaxidma::DmaError;
+
+pub use esys::xstatus::XStatus;
+
+/// This function converts a status code returned by the drivers into a Result.
+/// XST_SUCCESS becomes Ok(()), and the other codes become Err.
+pub fn check<S: Into<XStatus>>(status: S) -> Result<(), XStatus> {
+    match status.into() {
+        XStatus::Success => Ok(()),
+        status => Err(status),
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Error
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for errors of all drivers.
+///
+/// Each driver error converts into it, so errors of different drivers
+/// can be propagated with `?` in one function.
+///
+/// # Example
+/// ```
+/// use embeddedsw_rs::{
+///     peripherals::Peripherals,
+///     xscugic::{CpuMask, RawInterrupt},
+///     xstatus::Error,
+/// };
+///
+/// fn setup() -> Result<(), Error> {
+///     let mut p = Peripherals::take().unwrap();
+///     let gic = p.scugic(0)?;
+///     gic.software_intr(RawInterrupt(14), CpuMask::R5_0)?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// The XST_* status code returned by a driver.
+    Status(XStatus),
+    /// The error of taking a peripheral.
+    Peripheral(PeripheralError),
+    /// The error of the DMA driver.
+    #[cfg(feature = "xaxidma")]
+    Dma(DmaError),
+    /// The error of FatFs.
+    #[cfg(feature = "xilffs")]
+    Fs(FResult),
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Status(status) => write!(f, "{}", status),
+            Error::Peripheral(e) => write!(f, "{}", e),
+            #[cfg(feature = "xaxidma")]
+            Error::Dma(e) => write!(f, "{}", e),
+            #[cfg(feature = "xilffs")]
+            Error::Fs(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl core::error::Error for Error {}
+
+impl From<XStatus> for Error {
+    fn from(status: XStatus) -> Error {
+        Error::Status(status)
+    }
+}
+
+impl From<PeripheralError> for Error {
+    fn from(e: PeripheralError) -> Error {
+        Error::Peripheral(e)
+    }
+}
+
+#[cfg(feature = "xaxidma")]
+impl From<DmaError> for Error {
+    fn from(e: DmaError) -> Error {
+        Error::Dma(e)
+    }
+}
+
+#[cfg(feature = "xilffs")]
+impl From<FResult> for Error {
+    fn from(e: FResult) -> Error {
+        Error::Fs(e)
+    }
+}
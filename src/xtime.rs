@@ -0,0 +1,87 @@
+extern crate embeddedsw_sys;
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+use embeddedsw_sys as esys;
+
+use crate::xscugic::interrupt_free;
+
+/// The frequency of the global timer of `xtime_l.h`.
+pub const COUNTS_PER_SECOND: u64 = esys::COUNTS_PER_SECOND as u64;
+
+// The counter of Cortex-R5 is 32 bits, so the wraps are counted.
+static LAST: AtomicU32 = AtomicU32::new(0);
+static WRAPS: AtomicU32 = AtomicU32::new(0);
+
+//-------------------------------------------------------------------------------------------------
+// Instant
+//-------------------------------------------------------------------------------------------------
+
+/// A point of time of the global timer.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Instant {
+    ticks: u64,
+}
+
+impl Instant {
+    /// This function takes the current time.
+    ///
+    /// If the counter is 32 bits, it is extended to 64 bits by counting
+    /// its wraps when this function is called.
+    /// No interrupt counts them, so call it at least once in each period
+    /// of the counter (`2^32 / COUNTS_PER_SECOND` seconds).
+    /// Otherwise a wrap is missed, and the time goes back by one period.
+    pub fn now() -> Self {
+        Self { ticks: ticks() }
+    }
+
+    /// This function takes the ticks since the timer started.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// This function takes the time since the timer started.
+    pub fn since_boot(&self) -> Duration {
+        ticks_to_duration(self.ticks)
+    }
+
+    /// This function takes the time from the earlier instant.
+    /// If the earlier one is later, it returns zero.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.ticks.saturating_sub(earlier.ticks))
+    }
+
+    /// This function takes the time elapsed since the instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+fn ticks_to_duration(ticks: u64) -> Duration {
+    let secs = ticks / COUNTS_PER_SECOND;
+    let nanos = (ticks % COUNTS_PER_SECOND) * 1_000_000_000 / COUNTS_PER_SECOND;
+    Duration::new(secs, nanos as u32)
+}
+
+// XTime is u32 or u64 depending on the BSP.
+#[allow(clippy::unnecessary_cast)]
+fn ticks() -> u64 {
+    let mut now: esys::XTime = 0;
+    if size_of::<esys::XTime>() >= size_of::<u64>() {
+        unsafe { esys::XTime_GetTime(&mut now) };
+        return now as u64;
+    }
+    interrupt_free(|| {
+        unsafe { esys::XTime_GetTime(&mut now) };
+        let now = now as u32;
+        let mut wraps = WRAPS.load(Ordering::Relaxed);
+        if now < LAST.load(Ordering::Relaxed) {
+            wraps = wraps.wrapping_add(1);
+            WRAPS.store(wraps, Ordering::Relaxed);
+        }
+        LAST.store(now, Ordering::Relaxed);
+        ((wraps as u64) << 32) | now as u64
+    })
+}
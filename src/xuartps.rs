@@ -0,0 +1,787 @@
+extern crate embeddedsw_sys;
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+use embeddedsw_sys as esys;
+
+use crate::xscugic::{InterruptSource, XScuGic};
+use crate::xstatus::{self, XStatus};
+
+/// The depth of the TX and RX FIFOs in bytes.
+pub const FIFO_SIZE: usize = 64;
+
+//-------------------------------------------------------------------------------------------------
+// Format
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the number of data bits.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataBits {
+    Six = esys::XUARTPS_FORMAT_6_BITS,
+    Seven = esys::XUARTPS_FORMAT_7_BITS,
+    Eight = esys::XUARTPS_FORMAT_8_BITS,
+}
+
+/// Enumerates for the parity.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Parity {
+    None = esys::XUARTPS_FORMAT_NO_PARITY,
+    Even = esys::XUARTPS_FORMAT_EVEN_PARITY,
+    Odd = esys::XUARTPS_FORMAT_ODD_PARITY,
+    /// The parity bit is always 0.
+    Space = esys::XUARTPS_FORMAT_SPACE_PARITY,
+    /// The parity bit is always 1.
+    Mark = esys::XUARTPS_FORMAT_MARK_PARITY,
+}
+
+/// Enumerates for the number of stop bits.
+#[repr(u32)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopBits {
+    One = esys::XUARTPS_FORMAT_1_STOP_BIT,
+    OneAndHalf = esys::XUARTPS_FORMAT_1_5_STOP_BIT,
+    Two = esys::XUARTPS_FORMAT_2_STOP_BIT,
+}
+
+/// The data format of the UART.
+/// The default is 115200 bps, 8 data bits, no parity and 1 stop bit.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Format {
+    pub baud_rate: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            baud_rate: 115_200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// UartError
+//-------------------------------------------------------------------------------------------------
+
+/// Enumerates for the receive errors of the UART.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UartError {
+    /// The RX FIFO was full and a byte was lost.
+    Overrun,
+    /// A byte had a wrong parity bit.
+    Parity,
+    /// A byte had no valid stop bit.
+    Framing,
+}
+
+impl core::fmt::Display for UartError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            UartError::Overrun => write!(f, "RX FIFO overrun"),
+            UartError::Parity => write!(f, "parity error"),
+            UartError::Framing => write!(f, "framing error"),
+        }
+    }
+}
+
+impl core::error::Error for UartError {}
+
+//-------------------------------------------------------------------------------------------------
+// XUartPsConfig
+//-------------------------------------------------------------------------------------------------
+
+/// The configuration structure for UART instance.
+#[repr(C)]
+pub struct XUartPsConfig {
+    config: *mut esys::XUartPs_Config,
+}
+
+impl XUartPsConfig {
+    /// Look up the hardware configuration for XUartPs instance.
+    ///
+    /// # Errors
+    /// If this function cannot find device configuration,
+    /// it returns XST_DEVICE_NOT_FOUND.
+    pub fn lookup_config(device_id: u16) -> Result<Self, XStatus> {
+        let config = unsafe { esys::XUartPs_LookupConfig(device_id) };
+
+        if config.is_null() {
+            Err(XStatus::DeviceNotFound)
+        } else {
+            Ok(Self { config })
+        }
+    }
+
+    /// This function takes a base address of the UART.
+    pub fn get_base_addr(&self) -> usize {
+        unsafe { (*self.config).BaseAddress as usize }
+    }
+
+    /// This function takes the reference clock frequency of the UART.
+    pub fn input_clock_hz(&self) -> u32 {
+        unsafe { (*self.config).InputClockHz }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// XUartPs
+//-------------------------------------------------------------------------------------------------
+
+/// The UART instance struct in polled mode.
+///
+/// The console (`print!` and `console::stdin`) drives the UART of
+/// `STDOUT_BASEADDRESS` with its registers, so do not take that UART
+/// here while the console is used.
+///
+/// # Example
+/// UART1 talks to a sensor at 9600 bps with even parity,
+/// while UART0 stays the console.
+/// ```
+/// use embeddedsw_rs::{
+///     peripherals::Peripherals,
+///     raw,
+///     xuartps::{Format, Parity},
+/// };
+///
+/// let mut p = Peripherals::take().unwrap();
+/// let uart = p.uartps(raw::XPAR_XUARTPS_1_DEVICE_ID as u16).unwrap();
+/// uart.set_format(&Format {
+///     baud_rate: 9600,
+///     parity: Parity::Even,
+///     ..Format::default()
+/// })
+/// .unwrap();
+///
+/// let mut n = 0;
+/// while n < 4 {
+///     n += uart.send(&b"READ"[n..]);
+/// }
+/// ```
+///
+/// With the `embedded-io` or `embedded-hal-nb` feature,
+/// it can be passed to the drivers of these traits.
+#[repr(C)]
+pub struct XUartPs {
+    inner: esys::XUartPs,
+}
+
+impl XUartPs {
+    /// This function initializes a XUartPs struct.
+    /// The UART is set to 115200 bps, 8N1 with all interrupts disabled.
+    /// Take an initialized instance with `Peripherals::uartps`.
+    ///
+    /// # Errors
+    /// If this function cannot initialize the instance,
+    /// it returns the XST_* status code.
+    pub(crate) fn cfg_initialize(
+        xuartps: &mut MaybeUninit<XUartPs>,
+        config: &XUartPsConfig,
+    ) -> Result<(), XStatus> {
+        let status = unsafe {
+            esys::XUartPs_CfgInitialize(
+                xuartps.as_mut_ptr() as *mut esys::XUartPs,
+                config.config,
+                config.get_base_addr() as _,
+            )
+        };
+
+        xstatus::check(status)
+    }
+
+    /// This function sets the baud rate.
+    ///
+    /// # Errors
+    /// If the baud rate cannot be made from the reference clock
+    /// within 3% error, it returns XST_UART_BAUD_ERROR.
+    pub fn set_baud_rate(&mut self, baud_rate: u32) -> Result<(), XStatus> {
+        let status =
+            unsafe { esys::XUartPs_SetBaudRate(&mut self.inner, baud_rate) };
+
+        xstatus::check(status)
+    }
+
+    /// This function sets the baud rate, data bits, parity and stop bits.
+    ///
+    /// # Errors
+    /// If the baud rate cannot be made, it returns XST_UART_BAUD_ERROR,
+    /// and the format is not changed.
+    pub fn set_format(&mut self, format: &Format) -> Result<(), XStatus> {
+        let mut raw = esys::XUartPsFormat {
+            BaudRate: format.baud_rate,
+            DataBits: format.data_bits as u32,
+            Parity: format.parity as u32,
+            StopBits: format.stop_bits as u8,
+        };
+        let status =
+            unsafe { esys::XUartPs_SetDataFormat(&mut self.inner, &mut raw) };
+
+        xstatus::check(status)
+    }
+
+    /// This function takes the current data format.
+    pub fn format(&mut self) -> Format {
+        let mut raw = esys::XUartPsFormat {
+            BaudRate: 0,
+            DataBits: 0,
+            Parity: 0,
+            StopBits: 0,
+        };
+        unsafe { esys::XUartPs_GetDataFormat(&mut self.inner, &mut raw) };
+
+        Format {
+            baud_rate: raw.BaudRate,
+            data_bits: match raw.DataBits {
+                esys::XUARTPS_FORMAT_6_BITS => DataBits::Six,
+                esys::XUARTPS_FORMAT_7_BITS => DataBits::Seven,
+                _ => DataBits::Eight,
+            },
+            parity: match raw.Parity {
+                esys::XUARTPS_FORMAT_EVEN_PARITY => Parity::Even,
+                esys::XUARTPS_FORMAT_ODD_PARITY => Parity::Odd,
+                esys::XUARTPS_FORMAT_SPACE_PARITY => Parity::Space,
+                esys::XUARTPS_FORMAT_MARK_PARITY => Parity::Mark,
+                _ => Parity::None,
+            },
+            stop_bits: match raw.StopBits as u32 {
+                esys::XUARTPS_FORMAT_1_5_STOP_BIT => StopBits::OneAndHalf,
+                esys::XUARTPS_FORMAT_2_STOP_BIT => StopBits::Two,
+                _ => StopBits::One,
+            },
+        }
+    }
+
+    /// This function sets the number of bytes in the RX FIFO
+    /// which raises the RX trigger interrupt (1 to 63, 0 disables it).
+    pub fn set_rx_fifo_threshold(&mut self, level: u8) {
+        let level = level.min(FIFO_SIZE as u8 - 1);
+        unsafe { esys::XUartPs_SetFifoThreshold(&mut self.inner, level) }
+    }
+
+    /// This function sets the number of bytes in the TX FIFO
+    /// which raises the TX trigger interrupt (1 to 63).
+    pub fn set_tx_fifo_threshold(&mut self, level: u8) {
+        let level = (level as u32).clamp(1, esys::XUARTPS_TXWM_MASK);
+        unsafe {
+            Self::write_reg(self.base_addr(), esys::XUARTPS_TXWM_OFFSET, level)
+        }
+    }
+
+    /// This function sets the receive timeout in units of 4 bit times.
+    /// The timeout interrupt is raised when no byte is received for
+    /// the time while the RX FIFO is not empty. 0 disables it.
+    pub fn set_recv_timeout(&mut self, timeout: u8) {
+        unsafe { esys::XUartPs_SetRecvTimeout(&mut self.inner, timeout) }
+    }
+
+    /// This function writes bytes into the TX FIFO until it is full,
+    /// and takes the number of bytes written.
+    /// It does not wait, so it may write no bytes.
+    ///
+    /// The FIFO is written directly, because `XUartPs_Send` enables
+    /// the TX-empty interrupt while RX interrupts are enabled,
+    /// and nothing would disable it again.
+    pub fn send(&mut self, bytes: &[u8]) -> usize {
+        let base = self.base_addr();
+        let mut n = 0;
+        while n < bytes.len() && !self.status(esys::XUARTPS_SR_TXFULL) {
+            unsafe {
+                Self::write_reg(
+                    base,
+                    esys::XUARTPS_FIFO_OFFSET,
+                    bytes[n] as u32,
+                )
+            };
+            n += 1;
+        }
+        n
+    }
+
+    /// This function reads bytes from the RX FIFO until it is empty,
+    /// and takes the number of bytes read.
+    /// It does not wait, so it may read no bytes.
+    pub fn recv(&mut self, buf: &mut [u8]) -> usize {
+        unsafe {
+            esys::XUartPs_Recv(
+                &mut self.inner,
+                buf.as_mut_ptr(),
+                buf.len() as u32,
+            ) as usize
+        }
+    }
+
+    /// This function checks whether the TX FIFO still has bytes to send.
+    pub fn is_sending(&mut self) -> bool {
+        unsafe { esys::XUartPs_IsSending(&mut self.inner) != 0 }
+    }
+
+    /// This function waits until all bytes in the TX FIFO are sent.
+    pub fn flush(&mut self) {
+        while !self.status(esys::XUARTPS_SR_TXEMPTY) {}
+    }
+
+    /// This function takes the receive error which has occurred
+    /// since the last call, and clears it.
+    /// When several errors have occurred, the overrun is reported first.
+    pub fn take_error(&mut self) -> Option<UartError> {
+        let base = self.base_addr();
+        let mask = esys::XUARTPS_IXR_OVER
+            | esys::XUARTPS_IXR_PARITY
+            | esys::XUARTPS_IXR_FRAMING;
+        let isr = unsafe { Self::read_reg(base, esys::XUARTPS_ISR_OFFSET) };
+        let errors = isr & mask;
+        if errors == 0 {
+            return None;
+        }
+        // the bits are cleared by writing 1.
+        unsafe { Self::write_reg(base, esys::XUARTPS_ISR_OFFSET, errors) };
+
+        if errors & esys::XUARTPS_IXR_OVER != 0 {
+            Some(UartError::Overrun)
+        } else if errors & esys::XUARTPS_IXR_PARITY != 0 {
+            Some(UartError::Parity)
+        } else {
+            Some(UartError::Framing)
+        }
+    }
+
+    /// This function receives in the background into the ring buffer.
+    ///
+    /// It connects the handler to the interrupt of the UART, and enables
+    /// the RX trigger, timeout, overrun, parity and framing interrupts.
+    /// The handler must call `RxProducer::on_interrupt` of the same ring.
+    /// The RX trigger level and timeout are the ones set by
+    /// `set_rx_fifo_threshold` and `set_recv_timeout`
+    /// (8 bytes and 4 bit times after `cfg_initialize`).
+    ///
+    /// While it is enabled, read bytes from the ring instead of `recv`.
+    ///
+    /// # Errors
+    /// If the handler cannot be connected, it returns the status.
+    ///
+    /// # Example
+    /// ```
+    /// use core::ptr::addr_of_mut;
+    /// use embeddedsw_rs::{
+    ///     xscugic::Interrupt,
+    ///     xuartps::{RxProducer, RxRing},
+    /// };
+    ///
+    /// static UART1_RX: RxRing<1024> = RxRing::new();
+    /// // the handler is the only user after it is set.
+    /// static mut UART1_PRODUCER: Option<RxProducer<1024>> = None;
+    ///
+    /// extern "C" fn uart1_handler() {
+    ///     if let Some(producer) = unsafe { &mut *addr_of_mut!(UART1_PRODUCER) } {
+    ///         producer.on_interrupt();
+    ///     }
+    /// }
+    ///
+    /// let (producer, mut consumer) = UART1_RX.split().unwrap();
+    /// // set before the interrupt is enabled.
+    /// unsafe { UART1_PRODUCER = Some(producer) };
+    /// uart.enable_rx_interrupt(&UART1_RX, gic, Interrupt::Uart1, uart1_handler)
+    ///     .unwrap();
+    ///
+    /// let mut buf = [0; 64];
+    /// loop {
+    ///     let n = consumer.read(&mut buf);
+    ///     // handle buf[..n]
+    /// }
+    /// ```
+    pub fn enable_rx_interrupt<const N: usize>(
+        &mut self,
+        ring: &'static RxRing<N>,
+        gic: &mut XScuGic,
+        id: impl InterruptSource + Copy,
+        handler: extern "C" fn(),
+    ) -> Result<(), XStatus> {
+        let base = self.base_addr();
+        unsafe {
+            Self::write_reg(base, esys::XUARTPS_IDR_OFFSET, RX_INTERRUPTS);
+            Self::write_reg(base, esys::XUARTPS_ISR_OFFSET, RX_INTERRUPTS);
+        }
+        ring.base.store(base, Ordering::Release);
+
+        gic.connect(id, Some(handler))?;
+        gic.enable(id);
+        unsafe {
+            Self::write_reg(base, esys::XUARTPS_IER_OFFSET, RX_INTERRUPTS)
+        };
+        Ok(())
+    }
+
+    /// This function stops receiving in the background.
+    /// The bytes left in the ring can still be read.
+    pub fn disable_rx_interrupt(
+        &mut self,
+        gic: &mut XScuGic,
+        id: impl InterruptSource + Copy,
+    ) {
+        unsafe {
+            Self::write_reg(
+                self.base_addr(),
+                esys::XUARTPS_IDR_OFFSET,
+                RX_INTERRUPTS,
+            )
+        };
+        gic.disable(id);
+        gic.disconnect(id);
+    }
+
+    /// This function takes the base address of the UART.
+    pub fn base_addr(&self) -> usize {
+        self.inner.Config.BaseAddress as usize
+    }
+
+    fn status(&self, bit: u32) -> bool {
+        unsafe {
+            Self::read_reg(self.base_addr(), esys::XUARTPS_SR_OFFSET) & bit != 0
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn write_reg(base_addr: usize, offset: u32, data: u32) {
+        core::ptr::write_volatile(
+            (base_addr + offset as usize) as *mut u32,
+            data,
+        )
+    }
+
+    #[inline(always)]
+    unsafe fn read_reg(base_addr: usize, offset: u32) -> u32 {
+        core::ptr::read_volatile((base_addr + offset as usize) as *const u32)
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// RxRing
+//-------------------------------------------------------------------------------------------------
+
+/// The interrupts which `enable_rx_interrupt` enables.
+const RX_INTERRUPTS: u32 = esys::XUARTPS_IXR_RXOVR
+    | esys::XUARTPS_IXR_TOUT
+    | esys::XUARTPS_IXR_OVER
+    | esys::XUARTPS_IXR_PARITY
+    | esys::XUARTPS_IXR_FRAMING;
+
+/// The numbers of receive errors since the ring was created.
+///
+/// The UART only flags that an error has occurred, so the errors are
+/// counted per interrupt which reports them, not per byte.
+/// Only `dropped` is counted in bytes.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct RxErrors {
+    /// Interrupts which reported bytes lost because the RX FIFO was full.
+    pub overrun: u32,
+    /// Interrupts which reported bytes with a wrong parity bit.
+    pub parity: u32,
+    /// Interrupts which reported bytes without a valid stop bit.
+    pub framing: u32,
+    /// Bytes dropped because the ring was full.
+    pub dropped: u32,
+}
+
+/// A lock-free ring buffer which the UART interrupt fills.
+///
+/// `split` takes the only producer, for the interrupt handler,
+/// and the only consumer, for the main loop, so neither masks interrupts.
+/// `N` must be a power of two, and the ring holds up to `N` bytes.
+///
+/// See `XUartPs::enable_rx_interrupt` for an example.
+pub struct RxRing<const N: usize> {
+    buf: UnsafeCell<[u8; N]>,
+    /// The count of bytes written, owned by the producer.
+    head: AtomicUsize,
+    /// The count of bytes read, owned by the consumer.
+    tail: AtomicUsize,
+    /// The base address of the UART, set by `enable_rx_interrupt`.
+    base: AtomicUsize,
+    overrun: AtomicU32,
+    parity: AtomicU32,
+    framing: AtomicU32,
+    dropped: AtomicU32,
+    /// Whether the producer and the consumer are taken.
+    split: AtomicBool,
+}
+
+// `buf` is only accessed through the producer and the consumer,
+// which `split` hands out once.
+unsafe impl<const N: usize> Sync for RxRing<N> {}
+
+impl<const N: usize> RxRing<N> {
+    /// This function creates an empty ring.
+    pub const fn new() -> Self {
+        const { assert!(N.is_power_of_two()) };
+        Self {
+            buf: UnsafeCell::new([0; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            base: AtomicUsize::new(0),
+            overrun: AtomicU32::new(0),
+            parity: AtomicU32::new(0),
+            framing: AtomicU32::new(0),
+            dropped: AtomicU32::new(0),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// This function takes the producer and the consumer of the ring.
+    /// If they have been taken, it returns None.
+    pub fn split(&'static self) -> Option<(RxProducer<N>, RxConsumer<N>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+        Some((RxProducer { ring: self }, RxConsumer { ring: self }))
+    }
+
+    /// This function takes the number of bytes in the ring.
+    pub fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        self.head.load(Ordering::Acquire).wrapping_sub(tail)
+    }
+
+    /// This function checks whether the ring has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// This function takes the numbers of receive errors.
+    pub fn errors(&self) -> RxErrors {
+        RxErrors {
+            overrun: self.overrun.load(Ordering::Relaxed),
+            parity: self.parity.load(Ordering::Relaxed),
+            framing: self.framing.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<const N: usize> Default for RxRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The writer of a `RxRing`, which the interrupt handler owns.
+pub struct RxProducer<const N: usize> {
+    ring: &'static RxRing<N>,
+}
+
+impl<const N: usize> RxProducer<N> {
+    /// This function moves the RX FIFO into the ring and counts the errors.
+    /// Call it from the interrupt handler of the UART.
+    pub fn on_interrupt(&mut self) {
+        let ring = self.ring;
+        let base = ring.base.load(Ordering::Acquire);
+        if base == 0 {
+            return;
+        }
+        let isr = unsafe { XUartPs::read_reg(base, esys::XUARTPS_ISR_OFFSET) }
+            & RX_INTERRUPTS;
+        // clear first, so bytes arriving while draining raise it again.
+        unsafe { XUartPs::write_reg(base, esys::XUARTPS_ISR_OFFSET, isr) };
+
+        if isr & esys::XUARTPS_IXR_OVER != 0 {
+            ring.overrun.fetch_add(1, Ordering::Relaxed);
+        }
+        if isr & esys::XUARTPS_IXR_PARITY != 0 {
+            ring.parity.fetch_add(1, Ordering::Relaxed);
+        }
+        if isr & esys::XUARTPS_IXR_FRAMING != 0 {
+            ring.framing.fetch_add(1, Ordering::Relaxed);
+        }
+
+        let mut head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+        let mut dropped = 0;
+        while unsafe { XUartPs::read_reg(base, esys::XUARTPS_SR_OFFSET) }
+            & esys::XUARTPS_SR_RXEMPTY
+            == 0
+        {
+            let b =
+                unsafe { XUartPs::read_reg(base, esys::XUARTPS_FIFO_OFFSET) };
+            if head.wrapping_sub(tail) == N {
+                // keep draining the FIFO, or the interrupt stays raised.
+                dropped += 1;
+                continue;
+            }
+            unsafe { (*ring.buf.get())[head % N] = b as u8 };
+            head = head.wrapping_add(1);
+        }
+        ring.head.store(head, Ordering::Release);
+        if dropped > 0 {
+            ring.dropped.fetch_add(dropped, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The reader of a `RxRing`, which the main loop owns.
+pub struct RxConsumer<const N: usize> {
+    ring: &'static RxRing<N>,
+}
+
+impl<const N: usize> RxConsumer<N> {
+    /// This function takes a byte from the ring.
+    pub fn pop(&mut self) -> Option<u8> {
+        let ring = self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        if ring.head.load(Ordering::Acquire) == tail {
+            return None;
+        }
+        let b = unsafe { (*ring.buf.get())[tail % N] };
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Some(b)
+    }
+
+    /// This function moves bytes from the ring into the buffer,
+    /// and takes the number of bytes read. It does not wait.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let ring = self.ring;
+        let mut tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        let n = head.wrapping_sub(tail).min(buf.len());
+        for b in &mut buf[..n] {
+            *b = unsafe { (*ring.buf.get())[tail % N] };
+            tail = tail.wrapping_add(1);
+        }
+        ring.tail.store(tail, Ordering::Release);
+        n
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// embedded-io
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for UartError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::InvalidData
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::ErrorType for XUartPs {
+    type Error = UartError;
+}
+
+/// It waits until at least one byte is received.
+/// A receive error is returned before the bytes after it.
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Read for XUartPs {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, UartError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if let Some(e) = self.take_error() {
+                return Err(e);
+            }
+            let n = self.recv(buf);
+            if n > 0 {
+                return Ok(n);
+            }
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::ReadReady for XUartPs {
+    fn read_ready(&mut self) -> Result<bool, UartError> {
+        Ok(!self.status(esys::XUARTPS_SR_RXEMPTY))
+    }
+}
+
+/// It waits until at least one byte is written into the TX FIFO.
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Write for XUartPs {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, UartError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.send(buf);
+            if n > 0 {
+                return Ok(n);
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), UartError> {
+        XUartPs::flush(self);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::WriteReady for XUartPs {
+    fn write_ready(&mut self) -> Result<bool, UartError> {
+        Ok(!self.status(esys::XUARTPS_SR_TXFULL))
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// embedded-hal-nb
+//-------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "embedded-hal-nb")]
+impl embedded_hal_nb::serial::Error for UartError {
+    fn kind(&self) -> embedded_hal_nb::serial::ErrorKind {
+        use embedded_hal_nb::serial::ErrorKind;
+
+        match self {
+            UartError::Overrun => ErrorKind::Overrun,
+            UartError::Parity => ErrorKind::Parity,
+            UartError::Framing => ErrorKind::FrameFormat,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-nb")]
+impl embedded_hal_nb::serial::ErrorType for XUartPs {
+    type Error = UartError;
+}
+
+#[cfg(feature = "embedded-hal-nb")]
+impl embedded_hal_nb::serial::Read<u8> for XUartPs {
+    fn read(&mut self) -> embedded_hal_nb::nb::Result<u8, UartError> {
+        if let Some(e) = self.take_error() {
+            return Err(embedded_hal_nb::nb::Error::Other(e));
+        }
+        let mut byte = 0;
+        match self.recv(core::slice::from_mut(&mut byte)) {
+            0 => Err(embedded_hal_nb::nb::Error::WouldBlock),
+            _ => Ok(byte),
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-nb")]
+impl embedded_hal_nb::serial::Write<u8> for XUartPs {
+    fn write(
+        &mut self,
+        word: u8,
+    ) -> embedded_hal_nb::nb::Result<(), UartError> {
+        match self.send(&[word]) {
+            0 => Err(embedded_hal_nb::nb::Error::WouldBlock),
+            _ => Ok(()),
+        }
+    }
+
+    fn flush(&mut self) -> embedded_hal_nb::nb::Result<(), UartError> {
+        if self.status(esys::XUARTPS_SR_TXEMPTY) {
+            Ok(())
+        } else {
+            Err(embedded_hal_nb::nb::Error::WouldBlock)
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Decoder of the crash records written by `embeddedsw_rs::crash`.
+//!
+//! The input is a file written by `CrashRecord::write_to`,
+//! or a dump of the memory which contains the `.crash_record` section,
+//! for example `mrd -bin -file ocm.bin 0xfffc0000 0x10000` of xsct.
+//!
+//! ```text
+//! crashdump <file>
+//! ```
+use std::{env, fmt::Write, fs, process::ExitCode};
+
+// The format must be the same as src/crash.rs.
+const MAGIC_SEALED: u32 = 0x4853_5243;
+const MAGIC_RECORDING: u32 = 0x474c_5243;
+const VERSION: u32 = 1;
+const SIZE: usize = 768;
+const CRC_START: usize = 16;
+const MESSAGE_OFFSET: usize = 52;
+const MESSAGE_LEN: usize = 200;
+const LOG_HEAD_OFFSET: usize = 252;
+const LOG_OFFSET: usize = 256;
+const LOG_LINES: usize = 8;
+const LINE_LEN: usize = 60;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("usage: {} <file>", args[0]);
+        return ExitCode::FAILURE;
+    }
+    let bytes = match fs::read(&args[1]) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("cannot read {}: {}", args[1], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // records are aligned to 32 bytes in memory dumps.
+    let found = (0..bytes.len().saturating_sub(SIZE - 1))
+        .step_by(32)
+        .find(|&offset| word(&bytes[offset..], 0) == MAGIC_SEALED);
+    let offset = match found {
+        Some(offset) => offset,
+        None => {
+            if (0..bytes.len().saturating_sub(SIZE - 1))
+                .step_by(32)
+                .any(|offset| word(&bytes[offset..], 0) == MAGIC_RECORDING)
+            {
+                eprintln!("no crash was recorded");
+            } else {
+                eprintln!("no crash record in {}", args[1]);
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match decode(&bytes[offset..offset + SIZE]) {
+        Ok(report) => {
+            print!("{}", report);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("invalid crash record at offset 0x{:x}: {}", offset, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Decodes the record into the report to print.
+fn decode(record: &[u8]) -> Result<String, String> {
+    let version = word(record, 4);
+    if version != VERSION {
+        return Err(format!("unsupported version {}", version));
+    }
+    let size = word(record, 8) as usize;
+    if size != SIZE {
+        return Err(format!("unexpected size {}", size));
+    }
+    let crc = crc32(&record[CRC_START..SIZE]);
+    if word(record, 12) != crc {
+        return Err(format!(
+            "CRC mismatch (0x{:08x} != 0x{:08x})",
+            word(record, 12),
+            crc
+        ));
+    }
+
+    let kind = match word(record, 16) {
+        1 => "Panic".to_string(),
+        2 => "Fault".to_string(),
+        3 => "User".to_string(),
+        kind => format!("unknown kind {}", kind),
+    };
+    let mut report = String::new();
+    writeln!(report, "crash record: {}", kind).unwrap();
+    writeln!(
+        report,
+        "{}",
+        text(&record[MESSAGE_OFFSET..], word(record, 48), MESSAGE_LEN)
+    )
+    .unwrap();
+    writeln!(
+        report,
+        "LR 0x{:08x} SP 0x{:08x} CPSR 0x{:08x}",
+        word(record, 20),
+        word(record, 24),
+        word(record, 28)
+    )
+    .unwrap();
+    writeln!(
+        report,
+        "DFSR 0x{:08x} DFAR 0x{:08x} IFSR 0x{:08x} IFAR 0x{:08x}",
+        word(record, 32),
+        word(record, 36),
+        word(record, 40),
+        word(record, 44)
+    )
+    .unwrap();
+
+    let head = word(record, LOG_HEAD_OFFSET) as usize;
+    let count = head.min(LOG_LINES);
+    if count > 0 {
+        writeln!(report, "last {} of {} log lines:", count, head).unwrap();
+    }
+    for i in head - count..head {
+        let line = &record[LOG_OFFSET + (i % LOG_LINES) * (4 + LINE_LEN)..];
+        writeln!(report, "| {}", text(&line[4..], word(line, 0), LINE_LEN))
+            .unwrap();
+    }
+    Ok(report)
+}
+
+fn word(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn text(bytes: &[u8], len: u32, max: usize) -> String {
+    String::from_utf8_lossy(&bytes[..(len as usize).min(max)]).into_owned()
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |mut c, b| {
+        c ^= *b as u32;
+        for _ in 0..8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+        }
+        c
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A panic record with two log lines, as `embeddedsw_rs::crash` seals it.
+    fn record() -> Vec<u8> {
+        let mut r = vec![0u8; SIZE];
+        let mut put = |offset: usize, value: u32| {
+            r[offset..offset + 4].copy_from_slice(&value.to_le_bytes())
+        };
+        put(0, MAGIC_SEALED);
+        put(4, VERSION);
+        put(8, SIZE as u32);
+        put(16, 1);
+        put(20, 0x0010_0abc);
+        put(24, 0x0003_fff0);
+        put(28, 0x6000_001f);
+        put(48, 4);
+        put(LOG_HEAD_OFFSET, 2);
+        put(LOG_OFFSET, 7);
+        put(LOG_OFFSET + 4 + LINE_LEN, 7);
+        r[MESSAGE_OFFSET..MESSAGE_OFFSET + 4].copy_from_slice(b"boom");
+        r[LOG_OFFSET + 4..LOG_OFFSET + 11].copy_from_slice(b"started");
+        let line = LOG_OFFSET + 4 + LINE_LEN + 4;
+        r[line..line + 7].copy_from_slice(b"running");
+        let crc = crc32(&r[CRC_START..]);
+        r[12..16].copy_from_slice(&crc.to_le_bytes());
+        r
+    }
+
+    #[test]
+    fn crc32_matches_ieee() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(word(&record(), 12), 0xcb0a_dc5f);
+    }
+
+    #[test]
+    fn decodes_known_record() {
+        assert_eq!(
+            decode(&record()).unwrap(),
+            "crash record: Panic\n\
+             boom\n\
+             LR 0x00100abc SP 0x0003fff0 CPSR 0x6000001f\n\
+             DFSR 0x00000000 DFAR 0x00000000 IFSR 0x00000000 IFAR 0x00000000\n\
+             last 2 of 2 log lines:\n\
+             | started\n\
+             | running\n"
+        );
+    }
+
+    #[test]
+    fn rejects_corrupted_record() {
+        let mut record = record();
+        record[MESSAGE_OFFSET] ^= 1;
+        assert!(decode(&record).unwrap_err().starts_with("CRC mismatch"));
+    }
+}
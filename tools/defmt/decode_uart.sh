@@ -0,0 +1,18 @@
+#!/bin/sh
+# Decode defmt frames which the firmware sends to the UART.
+#
+# usage: decode_uart.sh <firmware.elf> [device] [baud]
+#
+# It needs defmt-print: cargo install defmt-print
+set -e
+
+if [ $# -lt 1 ]; then
+    echo "usage: $0 <firmware.elf> [device] [baud]" >&2
+    exit 1
+fi
+ELF=$1
+DEV=${2:-/dev/ttyUSB1}
+BAUD=${3:-115200}
+
+stty -F "$DEV" "$BAUD" raw -echo
+exec defmt-print -e "$ELF" < "$DEV"
@@ -0,0 +1,68 @@
+#!/usr/bin/env python3
+"""Drain the defmt ring buffer of the R5 from Linux on the APU.
+
+The ring buffer must be placed in memory which Linux can map, such as OCM,
+with the `.defmt_ring` section of the linker script.
+Its address is the `DEFMT_RING` symbol of the firmware:
+
+    nm firmware.elf | grep DEFMT_RING
+
+usage (on the APU, as root):
+
+    drain_ring.py <address> | defmt-print -e firmware.elf
+
+The layout must be the same as `Ring` in src/defmt_logger.rs.
+"""
+import mmap
+import os
+import struct
+import sys
+import time
+
+ID = b"DEFMT RING"
+READ_OFFSET = 32
+BUF_OFFSET = 64
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.stderr.write(__doc__)
+        return 1
+    addr = int(sys.argv[1], 0)
+    page = mmap.PAGESIZE
+    base = addr & ~(page - 1)
+    offset = addr - base
+
+    fd = os.open("/dev/mem", os.O_RDWR | os.O_SYNC)
+    # map the header first to take the size.
+    mem = mmap.mmap(fd, offset + BUF_OFFSET, offset=base)
+    if mem[offset:offset + len(ID)] != ID:
+        sys.stderr.write("no defmt ring at 0x%x\n" % addr)
+        return 1
+    (size,) = struct.unpack_from("<I", mem, offset + 16)
+    mem.close()
+    mem = mmap.mmap(fd, offset + BUF_OFFSET + size, offset=base)
+
+    out = sys.stdout.buffer
+    dropped_seen = 0
+    while True:
+        write, dropped = struct.unpack_from("<II", mem, offset + 20)
+        (read,) = struct.unpack_from("<I", mem, offset + READ_OFFSET)
+        if dropped != dropped_seen:
+            sys.stderr.write("%d frames dropped\n" % (dropped - dropped_seen))
+            dropped_seen = dropped
+        if read == write:
+            time.sleep(0.01)
+            continue
+        start = offset + BUF_OFFSET
+        if read < write:
+            out.write(mem[start + read:start + write])
+        else:
+            out.write(mem[start + read:start + size])
+            out.write(mem[start:start + write])
+        out.flush()
+        struct.pack_into("<I", mem, offset + READ_OFFSET, write)
+
+
+if __name__ == "__main__":
+    sys.exit(main())